@@ -0,0 +1,118 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::Hash;
+
+use num_traits::CheckedAdd;
+use petgraph::visit::{IntoEdgeReferences, IntoNodeIdentifiers};
+
+use crate::algo::max_flow::network::Network;
+use crate::algo::max_flow::{MaxFlow, MaxFlowError, NodePair};
+use crate::algo::mcmf::EdgeWeight;
+use crate::int::Int;
+
+/// A max flow impl using Dinic's algorithm.
+///
+/// Every phase builds the level graph of the residual network with a BFS from the source and then
+/// saturates it with a blocking flow, found by depth-first search with current-arc pointers.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Dinic;
+
+impl MaxFlow for Dinic {
+    fn max_flow<G, I>(
+        &mut self,
+        g: G,
+        source: G::NodeId,
+        target: G::NodeId,
+    ) -> Result<BTreeMap<NodePair<G::NodeId>, I>, MaxFlowError>
+    where
+        G: IntoEdgeReferences<EdgeWeight = EdgeWeight<I, I>> + IntoNodeIdentifiers,
+        G::NodeId: Clone + Hash + Eq + Ord,
+        I: Int + CheckedAdd,
+    {
+        let mut r = Network::new(g, source, target);
+        if r.source != r.target {
+            while let Some(level) = levels(&r) {
+                blocking_flow(&mut r, level);
+            }
+        }
+        r.into_flows()
+    }
+}
+
+/// BFS from the source over the arcs with residual capacity. Returns `None` if the target is
+/// unreachable.
+fn levels<N, I: Int>(r: &Network<N, I>) -> Option<Vec<Option<usize>>> {
+    let mut level = vec![None; r.nodes.len()];
+    let mut queue = VecDeque::new();
+    level[r.source] = Some(0);
+    queue.push_back(r.source);
+
+    while let Some(u) = queue.pop_front() {
+        let lu = level[u].unwrap_or_default();
+        for arc in r.residual.first[u]..r.residual.first[u + 1] {
+            let v = r.residual.head[arc];
+            if r.residual.residual[arc] > I::zero() && level[v].is_none() {
+                level[v] = Some(lu + 1);
+                queue.push_back(v);
+            }
+        }
+    }
+
+    level[r.target].map(|_| level)
+}
+
+fn blocking_flow<N, I>(r: &mut Network<N, I>, mut level: Vec<Option<usize>>)
+where
+    N: Clone + Ord,
+    I: Int + CheckedAdd,
+{
+    let mut current = r.residual.first.clone();
+    let mut path: Vec<usize> = Vec::new();
+    let mut u = r.source;
+
+    loop {
+        if u == r.target {
+            let amount = path
+                .iter()
+                .map(|arc| r.residual.residual[*arc])
+                .min()
+                .unwrap_or_default();
+            path.iter().for_each(|arc| r.residual.augment(*arc, amount));
+
+            // retreat to the tail of the first saturated arc
+            let saturated = path
+                .iter()
+                .position(|arc| r.residual.residual[*arc] == I::zero())
+                .unwrap_or_default();
+            path.truncate(saturated);
+            u = path.last().map_or(r.source, |arc| r.residual.head[*arc]);
+            continue;
+        }
+
+        let lu = level[u];
+        let next = (current[u]..r.residual.first[u + 1]).find(|arc| {
+            r.residual.residual[*arc] > I::zero()
+                && matches!((lu, level[r.residual.head[*arc]]), (Some(lu), Some(lv)) if lv == lu + 1)
+        });
+        match next {
+            Some(arc) => {
+                current[u] = arc;
+                path.push(arc);
+                u = r.residual.head[arc];
+            }
+            None => {
+                // `u` is a dead end, so remove it from the level graph
+                current[u] = r.residual.first[u + 1];
+                level[u] = None;
+                match path.pop() {
+                    Some(arc) => {
+                        u = r.residual.tail(arc);
+                        current[u] += 1;
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec;
+use core::hash::Hash;
+
+use num_traits::CheckedAdd;
+use petgraph::visit::{IntoEdgeReferences, IntoNodeIdentifiers};
+
+use crate::algo::max_flow::network::Network;
+use crate::algo::max_flow::{MaxFlow, MaxFlowError, NodePair};
+use crate::algo::mcmf::EdgeWeight;
+use crate::int::Int;
+
+/// A max flow impl using the Edmonds-Karp algorithm, i.e. repeatedly augmenting along a shortest
+/// path found by BFS.
+///
+/// This is slow (`O(VE^2)`) but simple, which makes it a useful reference to test the other
+/// impls against.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EdmondsKarp;
+
+impl MaxFlow for EdmondsKarp {
+    fn max_flow<G, I>(
+        &mut self,
+        g: G,
+        source: G::NodeId,
+        target: G::NodeId,
+    ) -> Result<BTreeMap<NodePair<G::NodeId>, I>, MaxFlowError>
+    where
+        G: IntoEdgeReferences<EdgeWeight = EdgeWeight<I, I>> + IntoNodeIdentifiers,
+        G::NodeId: Clone + Hash + Eq + Ord,
+        I: Int + CheckedAdd,
+    {
+        let mut r = Network::new(g, source, target);
+        if r.source == r.target {
+            return r.into_flows();
+        }
+
+        loop {
+            // BFS from the source, remembering the arc over which every node was reached
+            let mut pred = vec![None; r.nodes.len()];
+            let mut queue = VecDeque::new();
+            queue.push_back(r.source);
+            while let Some(u) = queue.pop_front() {
+                for arc in r.residual.first[u]..r.residual.first[u + 1] {
+                    let v = r.residual.head[arc];
+                    if r.residual.residual[arc] > I::zero() && v != r.source && pred[v].is_none() {
+                        pred[v] = Some(arc);
+                        queue.push_back(v);
+                    }
+                }
+                if pred[r.target].is_some() {
+                    break;
+                }
+            }
+            if pred[r.target].is_none() {
+                break;
+            }
+
+            // find the bottleneck capacity of the path and augment along it
+            let mut amount = None;
+            let mut v = r.target;
+            while let Some(arc) = pred[v] {
+                amount = match amount {
+                    Some(a) if a <= r.residual.residual[arc] => Some(a),
+                    _ => Some(r.residual.residual[arc]),
+                };
+                v = r.residual.tail(arc);
+            }
+            let amount = amount.unwrap_or_default();
+            let mut v = r.target;
+            while let Some(arc) = pred[v] {
+                r.residual.augment(arc, amount);
+                v = r.residual.tail(arc);
+            }
+        }
+
+        r.into_flows()
+    }
+}
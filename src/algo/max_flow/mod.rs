@@ -0,0 +1,175 @@
+pub mod dinic;
+pub mod edmonds_karp;
+mod network;
+pub mod push_relabel;
+
+pub use dinic::Dinic;
+pub use edmonds_karp::EdmondsKarp;
+pub use push_relabel::{push_relabel_max_flow, push_relabel_min_cut, PushRelabel};
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use core::hash::Hash;
+
+use num_traits::CheckedAdd;
+use petgraph::visit::{IntoEdgeReferences, IntoNodeIdentifiers};
+
+use crate::algo::mcmf::EdgeWeight;
+use crate::int::Int;
+
+pub type NodePair<NodeId> = (NodeId, NodeId);
+
+/// A max flow together with a minimum cut separating the source from the target.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MinCut<NodeId, Int> {
+    /// The flow between every pair of nodes with a strictly positive flow
+    pub flow: BTreeMap<NodePair<NodeId>, Int>,
+    /// The nodes on the source side of the cut
+    pub source_side: BTreeSet<NodeId>,
+    /// The edges from the source side to the target side with their capacities. All of them are
+    /// saturated by a max flow, so these are the bottlenecks that keep it from growing.
+    pub edges: BTreeMap<NodePair<NodeId>, Int>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MaxFlowError {
+    ArithmeticOverflow,
+}
+
+/// The max flow algorithm.
+pub trait MaxFlow {
+    /// Computes a max flow from `source` to `target` in the weighted graph `g`.
+    ///
+    /// The edge weights in `g` are interpreted as edge capacities -- negative capacities are
+    /// treated the same as zero capacities and edge costs are ignored.
+    ///
+    /// Returns `BTreeMap` that maps ordered pairs of vertices to the flow between them. The map
+    /// only contains pairs of vertices with a strictly positive flow. Returns an error if an
+    /// arithmetic overflow occurred.
+    fn max_flow<G, I>(
+        &mut self,
+        g: G,
+        source: G::NodeId,
+        target: G::NodeId,
+    ) -> Result<BTreeMap<NodePair<G::NodeId>, I>, MaxFlowError>
+    where
+        G: IntoEdgeReferences<EdgeWeight = EdgeWeight<I, I>> + IntoNodeIdentifiers,
+        G::NodeId: Clone + Hash + Eq + Ord,
+        I: Int + CheckedAdd;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::Rng;
+    use alloc::vec::Vec;
+
+    use petgraph::graphmap::DiGraphMap;
+
+    use super::*;
+
+    type Graph = DiGraphMap<u8, EdgeWeight<i64, i64>>;
+
+    fn graph(edges: &[(u8, u8, i64)]) -> Graph {
+        DiGraphMap::from_edges(edges.iter().map(|(u, v, capacity)| {
+            (
+                *u,
+                *v,
+                EdgeWeight {
+                    cost: 0,
+                    capacity: *capacity,
+                },
+            )
+        }))
+    }
+
+    // Pseudo-random graphs over the nodes `0..n` with up to `m` edges, where `0` is the source and
+    // `n - 1` the target
+    fn graphs(n: u8, m: u64) -> impl Iterator<Item = Graph> {
+        let mut rng = Rng::new(0x2545_f491_4f6c_dd1d);
+        let mut next = move |n: u64| rng.below(n);
+        (0..200).map(move |_| {
+            let edges: Vec<_> = (0..next(m + 1))
+                .map(|_| {
+                    let (u, v) = (next(n.into()) as u8, next(n.into()) as u8);
+                    (u, v, next(12) as i64 - 2)
+                })
+                .filter(|(u, v, _)| u != v)
+                .collect();
+            let mut g = graph(&edges);
+            g.add_node(0);
+            g.add_node(n - 1);
+            g
+        })
+    }
+
+    // The value of `flow` after checking that it's a feasible flow from `0` to `target` in `g`
+    fn value(g: &Graph, target: u8, flow: &BTreeMap<NodePair<u8>, i64>) -> i64 {
+        let mut balance = BTreeMap::new();
+        for ((u, v), f) in flow {
+            assert!(*f > 0);
+            assert!(*f <= g.edge_weight(*u, *v).unwrap().capacity);
+            *balance.entry(*u).or_insert(0) -= f;
+            *balance.entry(*v).or_insert(0) += f;
+        }
+        assert!(balance
+            .iter()
+            .all(|(node, b)| *node == 0 || *node == target || *b == 0));
+        balance.get(&target).copied().unwrap_or(0)
+    }
+
+    #[test]
+    fn max_flow_of_a_textbook_network() {
+        let g = graph(&[
+            (0, 1, 16),
+            (0, 2, 13),
+            (1, 3, 12),
+            (2, 1, 4),
+            (2, 4, 14),
+            (3, 2, 9),
+            (3, 5, 20),
+            (4, 3, 7),
+            (4, 5, 4),
+        ]);
+        assert_eq!(value(&g, 5, &PushRelabel.max_flow(&g, 0, 5).unwrap()), 23);
+        assert_eq!(value(&g, 5, &Dinic.max_flow(&g, 0, 5).unwrap()), 23);
+        assert_eq!(value(&g, 5, &EdmondsKarp.max_flow(&g, 0, 5).unwrap()), 23);
+    }
+
+    #[test]
+    fn max_flow_algos_agree() {
+        for g in graphs(6, 14) {
+            let flow = value(&g, 5, &PushRelabel.max_flow(&g, 0, 5).unwrap());
+            assert_eq!(value(&g, 5, &Dinic.max_flow(&g, 0, 5).unwrap()), flow);
+            assert_eq!(value(&g, 5, &EdmondsKarp.max_flow(&g, 0, 5).unwrap()), flow);
+        }
+    }
+
+    #[test]
+    fn push_relabel_agrees_on_larger_graphs() {
+        // with enough relabels for the gap heuristic and the periodic global relabeling to kick in
+        for g in graphs(40, 200).take(50) {
+            let flow = value(&g, 39, &PushRelabel.max_flow(&g, 0, 39).unwrap());
+            assert_eq!(
+                value(&g, 39, &EdmondsKarp.max_flow(&g, 0, 39).unwrap()),
+                flow
+            );
+        }
+    }
+
+    #[test]
+    fn min_cut_capacity_is_the_max_flow() {
+        for g in graphs(6, 14) {
+            let cut = push_relabel_min_cut(&g, 0, 5).unwrap();
+            assert!(cut.source_side.contains(&0));
+            assert!(!cut.source_side.contains(&5));
+            for ((u, v), capacity) in &cut.edges {
+                assert!(cut.source_side.contains(u) && !cut.source_side.contains(v));
+                assert_eq!(cut.flow.get(&(*u, *v)), Some(capacity));
+            }
+            for (u, v, e) in g.all_edges() {
+                let crossing = cut.source_side.contains(&u) && !cut.source_side.contains(&v);
+                assert_eq!(cut.edges.contains_key(&(u, v)), crossing && e.capacity > 0);
+            }
+            assert_eq!(cut.edges.values().sum::<i64>(), value(&g, 5, &cut.flow));
+        }
+    }
+}
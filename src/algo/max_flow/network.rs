@@ -0,0 +1,129 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use core::cmp::max;
+
+use num_traits::CheckedAdd;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences, IntoNodeIdentifiers};
+
+use crate::algo::max_flow::{MaxFlowError, MinCut, NodePair};
+use crate::algo::mcmf::EdgeWeight;
+use crate::algo::residual::Residual;
+use crate::int::Int;
+
+/// A max flow problem, i.e. the residual network of a graph (see `Residual`) with the nodes that
+/// its indices stand for.
+pub(super) struct Network<N, I> {
+    pub nodes: Vec<N>,
+    pub residual: Residual<I>,
+    pub source: usize,
+    pub target: usize,
+}
+
+impl<N, I> Network<N, I>
+where
+    N: Clone + Ord,
+    I: Int + CheckedAdd,
+{
+    /// Panics if `source` or `target` isn't a node of `g`.
+    pub fn new<G>(g: G, source: N, target: N) -> Self
+    where
+        G: IntoEdgeReferences<EdgeWeight = EdgeWeight<I, I>, NodeId = N> + IntoNodeIdentifiers,
+    {
+        let mut node_map = BTreeMap::new();
+        let mut nodes = Vec::new();
+        for n in g.node_identifiers() {
+            node_map.insert(n.clone(), nodes.len());
+            nodes.push(n);
+        }
+
+        let edges: Vec<_> = g
+            .edge_references()
+            .map(|e| {
+                (
+                    node_map[&e.source()],
+                    node_map[&e.target()],
+                    max(e.weight().capacity, I::zero()),
+                    I::zero(),
+                )
+            })
+            .collect();
+
+        Self {
+            source: *node_map
+                .get(&source)
+                .expect("source node isn't in the graph"),
+            target: *node_map
+                .get(&target)
+                .expect("target node isn't in the graph"),
+            residual: Residual::new(nodes.len(), &edges),
+            nodes,
+        }
+    }
+
+    /// Returns the flow and the cut between the nodes in `source_side` and the rest.
+    pub fn into_cut(self, source_side: &[bool]) -> Result<MinCut<N, I>, MaxFlowError> {
+        let r = &self.residual;
+        let mut edges = BTreeMap::<_, I>::new();
+        for arc in r.edge_arcs.iter().copied() {
+            let (u, v) = (r.tail(arc), r.head[arc]);
+            let capacity = r.residual[arc] + r.residual[r.rev[arc]];
+            if capacity <= I::zero() || !source_side[u] || source_side[v] {
+                continue;
+            }
+            let entry = edges
+                .entry((self.nodes[u].clone(), self.nodes[v].clone()))
+                .or_default();
+            *entry = entry
+                .checked_add(&capacity)
+                .ok_or(MaxFlowError::ArithmeticOverflow)?;
+        }
+
+        let source_side = self
+            .nodes
+            .iter()
+            .zip(source_side)
+            .filter(|(_, s)| **s)
+            .map(|(n, _)| n.clone())
+            .collect::<BTreeSet<_>>();
+
+        Ok(MinCut {
+            flow: self.into_flows()?,
+            source_side,
+            edges,
+        })
+    }
+
+    /// Returns the net flow between every pair of nodes with a strictly positive flow.
+    pub fn into_flows(self) -> Result<BTreeMap<NodePair<N>, I>, MaxFlowError> {
+        let r = &self.residual;
+        let mut net = BTreeMap::<_, I>::new();
+        for (arc, flow) in r.edge_arcs.iter().zip(r.flows()) {
+            if flow <= I::zero() {
+                continue;
+            }
+            let (u, v) = (r.tail(*arc), r.head[*arc]);
+            let (key, flow) = if u < v {
+                ((u, v), flow)
+            } else {
+                ((v, u), -flow)
+            };
+            let entry = net.entry(key).or_default();
+            *entry = entry
+                .checked_add(&flow)
+                .ok_or(MaxFlowError::ArithmeticOverflow)?;
+        }
+
+        Ok(net
+            .into_iter()
+            .filter(|(_, flow)| *flow != I::zero())
+            .map(|((u, v), flow)| {
+                let (u, v) = (self.nodes[u].clone(), self.nodes[v].clone());
+                if flow > I::zero() {
+                    ((u, v), flow)
+                } else {
+                    ((v, u), -flow)
+                }
+            })
+            .collect())
+    }
+}
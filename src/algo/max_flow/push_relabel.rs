@@ -0,0 +1,337 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+use core::hash::Hash;
+
+use num_traits::CheckedAdd;
+use petgraph::visit::{IntoEdgeReferences, IntoNodeIdentifiers};
+
+use crate::algo::max_flow::network::Network;
+use crate::algo::max_flow::{MaxFlow, MaxFlowError, MinCut, NodePair};
+use crate::algo::mcmf::EdgeWeight;
+use crate::algo::residual::Residual;
+use crate::int::Int;
+
+/// A max flow impl using the push-relabel method.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PushRelabel;
+
+impl MaxFlow for PushRelabel {
+    fn max_flow<G, I>(
+        &mut self,
+        g: G,
+        source: G::NodeId,
+        target: G::NodeId,
+    ) -> Result<BTreeMap<NodePair<G::NodeId>, I>, MaxFlowError>
+    where
+        G: IntoEdgeReferences<EdgeWeight = EdgeWeight<I, I>> + IntoNodeIdentifiers,
+        G::NodeId: Clone + Hash + Eq + Ord,
+        I: Int + CheckedAdd,
+    {
+        push_relabel_max_flow(g, source, target)
+    }
+}
+
+struct State<N, Int> {
+    nodes: Vec<N>,
+    residual: Residual<Int>,
+    source: usize,
+    target: usize,
+    excess: Vec<Int>,
+    label: Vec<usize>,
+    // The next arc to try pushing over for every node.
+    current: Vec<usize>,
+    // The active nodes bucketed by their label, so that we can always discharge an active node
+    // with the highest label.
+    active: Vec<Vec<usize>>,
+    highest_active: usize,
+    // The number of nodes with each label below `n`, used to detect gaps.
+    label_count: Vec<usize>,
+    // The number of relabels since the last global relabel.
+    relabels: usize,
+    // Scratch space for the BFS of the global relabel.
+    queue: VecDeque<usize>,
+}
+
+impl<N: Clone + Ord, I: Int + CheckedAdd> State<N, I> {
+    fn push(&mut self, u: usize, arc: usize) -> Result<(), MaxFlowError> {
+        let v = self.residual.head[arc];
+        debug_assert!(self.excess[u] > I::zero());
+        debug_assert!(self.label[u] == self.label[v] + 1);
+
+        let new_flow = min(self.excess[u], self.residual.residual[arc]);
+        self.add_excess(u, -new_flow)?;
+        self.add_excess(v, new_flow)?;
+        self.residual.augment(arc, new_flow);
+        Ok(())
+    }
+
+    fn can_push(&self, u: usize, arc: usize) -> bool {
+        self.residual.residual[arc] > I::zero()
+            && self.label[u] == self.label[self.residual.head[arc]] + 1
+    }
+
+    fn add_excess(&mut self, u: usize, amount: I) -> Result<(), MaxFlowError> {
+        debug_assert!(amount != I::zero());
+
+        // The target node never has any excess inflow, since it can just gobble it up.
+        if u == self.target {
+            return Ok(());
+        }
+
+        // We should never try to push more flow than the node has available.
+        // There is one special case: the start node always has non-positive
+        // excess flow.
+        debug_assert!(self.excess[u] <= I::zero() || self.excess[u] >= -amount);
+        let was_inactive = self.excess[u] == I::zero();
+        self.excess[u] = self.excess[u]
+            .checked_add(&amount)
+            .ok_or(MaxFlowError::ArithmeticOverflow)?;
+        if was_inactive && u != self.source {
+            // We weren't active before, but we are now.
+            self.activate(u);
+        }
+        Ok(())
+    }
+
+    fn activate(&mut self, u: usize) {
+        let label = self.label[u];
+        if label >= self.active.len() {
+            self.active.resize_with(label + 1, Vec::new);
+        }
+        self.active[label].push(u);
+        self.highest_active = max(self.highest_active, label);
+    }
+
+    fn set_label(&mut self, u: usize, label: usize) {
+        let n = self.label.len();
+        let old = core::mem::replace(&mut self.label[u], label);
+        if old < n {
+            self.label_count[old] -= 1;
+        }
+        if label < n {
+            self.label_count[label] += 1;
+        }
+    }
+
+    // Keep pushing excess flow to neighbors until we can't any more.
+    fn discharge(&mut self, u: usize) -> Result<(), MaxFlowError> {
+        while self.excess[u] > I::zero() {
+            let arc = self.current[u];
+            if arc == self.residual.first[u + 1] {
+                self.relabel(u);
+                self.current[u] = self.residual.first[u];
+            } else if self.can_push(u, arc) {
+                self.push(u, arc)?;
+            } else {
+                self.current[u] += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn relabel(&mut self, u: usize) {
+        let n = self.label.len();
+        let old_label = self.label[u];
+        let min_nbr_label = (self.residual.first[u]..self.residual.first[u + 1])
+            .filter(|arc| self.residual.residual[*arc] > I::zero())
+            .map(|arc| self.label[self.residual.head[arc]])
+            .min()
+            .expect("bug: tried to relabel a node with no outgoing edges");
+        self.set_label(u, min_nbr_label + 1);
+        self.relabels += 1;
+
+        // Gap heuristic: if no node is left with the old label, none of the nodes above it (and
+        // below `n`) can reach the target any more, so we lift them all above the source.
+        if old_label < n && self.label_count[old_label] == 0 {
+            for v in 0..n {
+                let label = self.label[v];
+                if old_label < label && label < n {
+                    self.set_label(v, n + 1);
+                }
+            }
+            self.rebuild_active();
+        }
+    }
+
+    // Set every label to the exact distance to the target in the residual graph, or to `n` plus
+    // the distance to the source for the nodes that can't reach the target.
+    fn global_relabel(&mut self) {
+        let n = self.label.len();
+        let (source, target) = (self.source, self.target);
+        // nodes that reach neither target nor source have no excess, so they can stay put
+        self.label.iter_mut().for_each(|label| *label = 2 * n);
+        self.label[target] = 0;
+        self.label[source] = n;
+
+        for root in [target, source] {
+            self.queue.push_back(root);
+            while let Some(v) = self.queue.pop_front() {
+                let label = self.label[v] + 1;
+                for arc in self.residual.first[v]..self.residual.first[v + 1] {
+                    let u = self.residual.head[arc];
+                    let rev = self.residual.rev[arc];
+                    if self.label[u] == 2 * n && self.residual.residual[rev] > I::zero() {
+                        self.label[u] = label;
+                        self.queue.push_back(u);
+                    }
+                }
+            }
+        }
+
+        self.label_count.iter_mut().for_each(|count| *count = 0);
+        for u in 0..n {
+            if self.label[u] < n {
+                self.label_count[self.label[u]] += 1;
+            }
+        }
+        self.current.copy_from_slice(&self.residual.first[..n]);
+        self.relabels = 0;
+        self.rebuild_active();
+    }
+
+    fn rebuild_active(&mut self) {
+        self.active.iter_mut().for_each(Vec::clear);
+        self.highest_active = 0;
+        for u in 0..self.label.len() {
+            if u != self.source && u != self.target && self.excess[u] > I::zero() {
+                self.activate(u);
+            }
+        }
+    }
+
+    fn pop_highest_active(&mut self) -> Option<usize> {
+        loop {
+            if let Some(u) = self.active.get_mut(self.highest_active).and_then(Vec::pop) {
+                return Some(u);
+            }
+            if self.highest_active == 0 {
+                return None;
+            }
+            self.highest_active -= 1;
+        }
+    }
+
+    fn new(network: Network<N, I>) -> Result<Self, MaxFlowError> {
+        let Network {
+            nodes,
+            mut residual,
+            source,
+            target,
+        } = network;
+        let n = nodes.len();
+        let mut excess = vec![I::zero(); n];
+
+        // saturate every arc out of the source
+        for arc in residual.first[source]..residual.first[source + 1] {
+            let cap = residual.residual[arc];
+            if cap > I::zero() {
+                let v = residual.head[arc];
+                residual.augment(arc, cap);
+                excess[v] = excess[v]
+                    .checked_add(&cap)
+                    .ok_or(MaxFlowError::ArithmeticOverflow)?;
+                excess[source] -= cap;
+            }
+        }
+
+        Ok(State {
+            current: residual.first[..n].to_vec(),
+            nodes,
+            residual,
+            source,
+            target,
+            excess,
+            label: vec![0; n],
+            active: Vec::new(),
+            highest_active: 0,
+            label_count: vec![0; n],
+            relabels: 0,
+            queue: VecDeque::new(),
+        })
+    }
+
+    fn run(&mut self) -> Result<(), MaxFlowError> {
+        if self.source == self.target {
+            return Ok(());
+        }
+
+        let n = self.label.len();
+        self.global_relabel();
+        while let Some(u) = self.pop_highest_active() {
+            self.discharge(u)?;
+            if self.relabels >= n {
+                self.global_relabel();
+            }
+        }
+        Ok(())
+    }
+
+    fn into_network(self) -> Network<N, I> {
+        Network {
+            nodes: self.nodes,
+            residual: self.residual,
+            source: self.source,
+            target: self.target,
+        }
+    }
+}
+
+/// Computes a max flow from `source` to `target` in the weighted graph `g` using the push-relabel
+/// algorithm.
+///
+/// Active nodes are discharged in highest-label order, the labels are periodically recomputed by
+/// a reverse BFS from `target` (global relabeling) and nodes that are cut off from `target` by a
+/// gap in the labels are lifted straight away (gap heuristic).
+///
+/// The edge weights in `g` are interpreted as edge capacities -- negative weights are treated the
+/// same as zero weights.
+///
+/// Returns `BTreeMap` that maps ordered pairs of vertices to the flow between them. The map only
+/// contains pairs of vertices with a strictly positive flow. Returns an error if an arithmetic
+/// overflow occurred.
+///
+/// Panics if `source` or `target` is an invalid node index for `g`.
+pub fn push_relabel_max_flow<G, I>(
+    g: G,
+    source: G::NodeId,
+    target: G::NodeId,
+) -> Result<BTreeMap<NodePair<G::NodeId>, I>, MaxFlowError>
+where
+    G: IntoEdgeReferences<EdgeWeight = EdgeWeight<I, I>> + IntoNodeIdentifiers,
+    G::NodeId: Clone + Hash + Eq + Ord,
+    I: Int + CheckedAdd,
+{
+    let mut state = State::new(Network::new(g, source, target))?;
+    state.run()?;
+    state.into_network().into_flows()
+}
+
+/// Computes a max flow from `source` to `target` in the weighted graph `g` (see
+/// `push_relabel_max_flow`) together with a minimum cut.
+///
+/// Once the algorithm terminates, the labels are recomputed one last time -- the nodes that can't
+/// reach `target` in the residual graph (i.e. those labelled `n` or higher) form the source side
+/// of the cut. When `g` is an obligation network over `Node<N>`s, the cut edges between two
+/// `Node::WithId`s are the obligations that keep more debt from being cleared.
+///
+/// Panics if `source` or `target` is an invalid node index for `g`.
+pub fn push_relabel_min_cut<G, I>(
+    g: G,
+    source: G::NodeId,
+    target: G::NodeId,
+) -> Result<MinCut<G::NodeId, I>, MaxFlowError>
+where
+    G: IntoEdgeReferences<EdgeWeight = EdgeWeight<I, I>> + IntoNodeIdentifiers,
+    G::NodeId: Clone + Hash + Eq + Ord,
+    I: Int + CheckedAdd,
+{
+    let mut state = State::new(Network::new(g, source, target))?;
+    state.run()?;
+
+    let n = state.label.len();
+    state.global_relabel();
+    let source_side: Vec<_> = state.label.iter().map(|label| *label >= n).collect();
+    state.into_network().into_cut(&source_side)
+}
@@ -0,0 +1,356 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::max;
+
+use num_traits::{CheckedAdd, CheckedSub};
+
+use crate::algo::mcmf::{EdgeFlows, EdgeWeight, FlowGraph};
+use crate::id::Id;
+use crate::int::Int;
+use crate::node::Node;
+
+/// A proof that a flow is a minimum-cost maximum flow of a `FlowGraph`, which can be checked by
+/// `Certificate::verify` without solving the problem again.
+///
+/// The flow is maximum because it saturates every edge leaving the `source_side` of a cut (and
+/// doesn't use any edge entering it), so it matches the capacity of that cut. It is of minimum cost
+/// among all flows of its value because the reduced cost `cost + potential(u) - potential(v)` of
+/// every edge with residual capacity is non-negative and that of every edge with flow is
+/// non-positive, i.e. the residual graph has no negative cycle.
+///
+/// Edges and nodes without an entry have a zero flow and a zero potential respectively.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate<N, I> {
+    /// The flow over every edge of the graph, including those from the source and to the sink
+    pub flow: EdgeFlows<N, I>,
+    /// The potential of every node
+    pub potentials: BTreeMap<Node<N>, I>,
+    /// The nodes on the source side of a minimum cut
+    pub source_side: BTreeSet<Node<N>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CertificateError<N, I> {
+    /// The certificate has a flow over an edge that isn't part of the graph
+    UnknownEdge { edge: (Node<N>, Node<N>) },
+    /// The flow over an edge is negative or exceeds its capacity
+    CapacityViolated { edge: (Node<N>, Node<N>) },
+    /// The flow into a node (other than the source and the sink) doesn't match the flow out of it
+    Unbalanced { node: Node<N> },
+    /// The flow out of the source doesn't match the claimed flow value
+    FlowValueMismatch { claimed: I, actual: I },
+    /// The source side of the cut doesn't contain the source or contains the sink
+    InvalidCut,
+    /// An edge that leaves the source side of the cut isn't saturated or an edge that enters it has
+    /// a flow, so the flow might not be maximum
+    CutViolated { edge: (Node<N>, Node<N>) },
+    /// The reduced cost of an edge violates the optimality conditions, so the flow might not be of
+    /// minimum cost
+    ReducedCostViolated { edge: (Node<N>, Node<N>) },
+    /// The reduced cost of an edge or the flow balance of a node overflows the integer type
+    Overflow,
+}
+
+impl<N, I> Certificate<N, I>
+where
+    N: Id,
+    I: Int,
+{
+    /// Builds the certificate of a min-cost max flow with potentials that satisfy the optimality
+    /// conditions, using the nodes that are reachable from the source in the residual graph as the
+    /// source side of the cut.
+    pub fn with_potentials(
+        graph: &FlowGraph<N, I, I>,
+        flow: EdgeFlows<N, I>,
+        potentials: BTreeMap<Node<N>, I>,
+    ) -> Self {
+        let mut residual = BTreeMap::<_, Vec<_>>::new();
+        for ((u, v), e) in graph {
+            let f = flow
+                .get(&(u.clone(), v.clone()))
+                .copied()
+                .unwrap_or_default();
+            if f < e.capacity {
+                residual.entry(u).or_default().push(v);
+            }
+            if f > I::zero() {
+                residual.entry(v).or_default().push(u);
+            }
+        }
+
+        let mut source_side = BTreeSet::new();
+        source_side.insert(Node::Source);
+        let mut stack = vec![&Node::Source];
+        while let Some(u) = stack.pop() {
+            for v in residual.get(u).into_iter().flatten() {
+                if source_side.insert((*v).clone()) {
+                    stack.push(*v);
+                }
+            }
+        }
+
+        Self {
+            flow,
+            potentials,
+            source_side,
+        }
+    }
+
+    /// Same as `with_potentials`, but the potentials are computed as the shortest path distances in
+    /// the residual graph (by the Bellman-Ford algorithm). Returns `None` if the residual graph has
+    /// a negative cycle, i.e. the flow isn't of minimum cost.
+    pub fn from_flow(graph: &FlowGraph<N, I, I>, flow: EdgeFlows<N, I>) -> Option<Self> {
+        let mut arcs = vec![];
+        for ((u, v), e) in graph {
+            let f = flow
+                .get(&(u.clone(), v.clone()))
+                .copied()
+                .unwrap_or_default();
+            if f < e.capacity {
+                arcs.push((u, v, e.cost));
+            }
+            if f > I::zero() {
+                arcs.push((v, u, -e.cost));
+            }
+        }
+
+        // all nodes start at a zero distance, as if reached from a virtual root
+        let mut potentials: BTreeMap<_, I> = graph
+            .keys()
+            .flat_map(|(u, v)| [u.clone(), v.clone()])
+            .map(|node| (node, I::zero()))
+            .collect();
+        for _ in 0..=potentials.len() {
+            let mut relaxed = false;
+            for (u, v, cost) in &arcs {
+                let distance = potentials[*u] + *cost;
+                if distance < potentials[*v] {
+                    *potentials.get_mut(*v).expect("node of arc") = distance;
+                    relaxed = true;
+                }
+            }
+            if !relaxed {
+                return Some(Self::with_potentials(graph, flow, potentials));
+            }
+        }
+
+        None
+    }
+
+    /// Checks that the certificate proves that its flow is a min-cost max flow of `graph` with the
+    /// claimed `flow_value`, in a single pass over the edges.
+    ///
+    /// As with the min-cost flow impls, negative capacities are treated the same as zero
+    /// capacities.
+    pub fn verify(
+        &self,
+        graph: &FlowGraph<N, I, I>,
+        flow_value: I,
+    ) -> Result<(), CertificateError<N, I>>
+    where
+        I: CheckedAdd + CheckedSub,
+    {
+        if let Some((edge, _)) = self.flow.iter().find(|(edge, _)| !graph.contains_key(edge)) {
+            return Err(CertificateError::UnknownEdge { edge: edge.clone() });
+        }
+        if !self.source_side.contains(&Node::Source) || self.source_side.contains(&Node::Sink) {
+            return Err(CertificateError::InvalidCut);
+        }
+
+        let mut balance = BTreeMap::<_, I>::new();
+        for ((u, v), EdgeWeight { cost, capacity }) in graph {
+            let edge = || (u.clone(), v.clone());
+            let capacity = max(*capacity, I::zero());
+            let f = self.flow.get(&edge()).copied().unwrap_or_default();
+            if f < I::zero() || f > capacity {
+                return Err(CertificateError::CapacityViolated { edge: edge() });
+            }
+            let out = balance.entry(u).or_default();
+            *out = out.checked_sub(&f).ok_or(CertificateError::Overflow)?;
+            let into = balance.entry(v).or_default();
+            *into = into.checked_add(&f).ok_or(CertificateError::Overflow)?;
+
+            match (self.source_side.contains(u), self.source_side.contains(v)) {
+                (true, false) if f != capacity => {
+                    return Err(CertificateError::CutViolated { edge: edge() })
+                }
+                (false, true) if f != I::zero() => {
+                    return Err(CertificateError::CutViolated { edge: edge() })
+                }
+                _ => {}
+            }
+
+            let potential = |node| self.potentials.get(node).copied().unwrap_or_default();
+            let reduced_cost = cost
+                .checked_add(&potential(u))
+                .and_then(|cost| cost.checked_sub(&potential(v)))
+                .ok_or(CertificateError::Overflow)?;
+            if (f < capacity && reduced_cost < I::zero())
+                || (f > I::zero() && reduced_cost > I::zero())
+            {
+                return Err(CertificateError::ReducedCostViolated { edge: edge() });
+            }
+        }
+
+        for (node, b) in &balance {
+            match node {
+                Node::Source => {
+                    let actual = I::zero().checked_sub(b).ok_or(CertificateError::Overflow)?;
+                    if actual != flow_value {
+                        return Err(CertificateError::FlowValueMismatch {
+                            claimed: flow_value,
+                            actual,
+                        });
+                    }
+                }
+                Node::Sink => {}
+                _ if *b != I::zero() => {
+                    return Err(CertificateError::Unbalanced {
+                        node: (*node).clone(),
+                    })
+                }
+                _ => {}
+            }
+        }
+        if !balance.contains_key(&Node::Source) && flow_value != I::zero() {
+            return Err(CertificateError::FlowValueMismatch {
+                claimed: flow_value,
+                actual: I::zero(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::mcmf::cost_scaling::CostScaling;
+    use crate::algo::mcmf::native_simplex::NativeSimplex;
+    use crate::algo::mcmf::primal_dual::PrimalDual;
+    use crate::algo::mcmf::CertifiedMinCostFlow;
+
+    type Edge = (Node<u64>, Node<u64>);
+
+    fn edge(u: u64, v: u64) -> Edge {
+        let node = |n| match n {
+            0 => Node::Source,
+            9 => Node::Sink,
+            n => Node::WithId(n),
+        };
+        (node(u), node(v))
+    }
+
+    // The cheapest route of the 10 units from the source through 1 is to send 5 over 2, 3 over 2
+    // and 3, and 2 over 3, at a cost of 19
+    fn graph() -> FlowGraph<u64, i64, i64> {
+        [
+            (0, 1, 10, 0),
+            (1, 2, 10, 1),
+            (1, 3, 10, 4),
+            (2, 3, 3, 1),
+            (2, 9, 5, 0),
+            (3, 9, 10, 0),
+        ]
+        .into_iter()
+        .map(|(u, v, capacity, cost)| (edge(u, v), EdgeWeight { cost, capacity }))
+        .collect()
+    }
+
+    #[test]
+    fn certificates_of_the_min_cost_flow_algos_verify() {
+        let g = graph();
+        let certified = [
+            PrimalDual::default().certified_min_cost_flow(&g).unwrap(),
+            CostScaling::default().certified_min_cost_flow(&g).unwrap(),
+            NativeSimplex::default()
+                .certified_min_cost_flow(&g)
+                .unwrap(),
+        ];
+        for (flow, certificate) in certified {
+            assert_eq!(flow, 10);
+            assert_eq!(certificate.verify(&g, flow), Ok(()));
+            assert_eq!(certificate.flow[&edge(1, 3)], 2);
+        }
+    }
+
+    #[test]
+    fn tampered_certificates_are_rejected() {
+        let g = graph();
+        let (flow, certificate) = PrimalDual::default().certified_min_cost_flow(&g).unwrap();
+
+        assert_eq!(
+            certificate.verify(&g, 9),
+            Err(CertificateError::FlowValueMismatch {
+                claimed: 9,
+                actual: 10
+            })
+        );
+
+        // a max flow that routes one more unit over the expensive edge
+        let mut tampered = certificate.clone();
+        for (e, delta) in [
+            (edge(1, 2), -1),
+            (edge(2, 9), -1),
+            (edge(1, 3), 1),
+            (edge(3, 9), 1),
+        ] {
+            *tampered.flow.get_mut(&e).unwrap() += delta;
+        }
+        assert!(matches!(
+            tampered.verify(&g, flow),
+            Err(CertificateError::ReducedCostViolated { .. })
+        ));
+
+        let mut tampered = certificate.clone();
+        *tampered.flow.get_mut(&edge(2, 3)).unwrap() += 1;
+        assert_eq!(
+            tampered.verify(&g, flow),
+            Err(CertificateError::CapacityViolated { edge: edge(2, 3) })
+        );
+
+        let mut tampered = certificate.clone();
+        tampered.flow.insert(edge(3, 1), 1);
+        assert_eq!(
+            tampered.verify(&g, flow),
+            Err(CertificateError::UnknownEdge { edge: edge(3, 1) })
+        );
+
+        let mut tampered = certificate;
+        tampered.source_side.insert(Node::Sink);
+        assert_eq!(tampered.verify(&g, flow), Err(CertificateError::InvalidCut));
+    }
+
+    #[test]
+    fn overflowing_certificates_are_rejected() {
+        let g = graph();
+        let (flow, mut certificate) = PrimalDual::default().certified_min_cost_flow(&g).unwrap();
+
+        // the reduced cost of the edges leaving 1 overflows
+        certificate.potentials.insert(Node::WithId(1), i64::MAX);
+        assert_eq!(
+            certificate.verify(&g, flow),
+            Err(CertificateError::Overflow)
+        );
+
+        // as does the flow out of the source
+        let g: FlowGraph<_, _, _> = [edge(0, 1), edge(0, 9), edge(1, 9)]
+            .into_iter()
+            .map(|edge| {
+                let weight = EdgeWeight {
+                    cost: 0,
+                    capacity: i64::MAX,
+                };
+                (edge, weight)
+            })
+            .collect();
+        let certificate = Certificate {
+            flow: g.keys().map(|edge| (*edge, i64::MAX)).collect(),
+            potentials: BTreeMap::new(),
+            source_side: [Node::Source].into_iter().collect(),
+        };
+        assert_eq!(certificate.verify(&g, 0), Err(CertificateError::Overflow));
+    }
+}
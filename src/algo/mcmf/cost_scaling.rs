@@ -0,0 +1,331 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+use core::marker::PhantomData;
+
+use num_traits::{CheckedAdd, CheckedMul, FromPrimitive};
+
+use crate::algo::max_flow::PushRelabel;
+use crate::algo::mcmf::certificate::Certificate;
+use crate::algo::mcmf::progress::{NoProgress, Progress};
+use crate::algo::mcmf::{CertifiedMinCostFlow, EdgeFlows, FlowGraph, FlowPaths, MinCostFlow};
+use crate::algo::residual::Residual;
+use crate::id::Id;
+use crate::int::Int;
+use crate::node::Node;
+
+/// The factor by which epsilon is divided after every refine phase.
+const ALPHA: u8 = 16;
+
+/// A min-cost flow impl using the Goldberg-Tarjan cost-scaling push-relabel method.
+///
+/// The max-flow/min-cost problem is solved as a min-cost circulation problem by adding a return
+/// edge from sink to source whose (negative) cost outweighs the cost of any path, so that the
+/// circulation of minimum cost routes as much flow as possible.
+///
+/// Costs are internally scaled by `n + 1` (where `n` is the number of nodes) and the prices can
+/// grow to a few times `n` times the largest scaled cost, so `Int` must be wide enough to hold
+/// `4 * (n + 1)^2 * (C + 1)` (where `C` is the sum of all absolute edge costs). Otherwise an error is
+/// returned.
+///
+/// The `progress` observer is called before every refine phase (when the flow is a feasible
+/// circulation) and can cancel the run, in which case the flow is completed to a max flow by
+/// `PushRelabel` regardless of the costs (see `MinCostFlow::cancelled`).
+#[derive(Clone)]
+pub struct CostScaling<Id, Int, P = NoProgress> {
+    progress: P,
+    cancelled: bool,
+    /// The number of refine phases of the last run
+    iterations: usize,
+    _phantom: PhantomData<(Id, Int)>,
+}
+
+impl<Id, Int> Default for CostScaling<Id, Int> {
+    fn default() -> Self {
+        Self {
+            progress: NoProgress,
+            cancelled: false,
+            iterations: 0,
+            _phantom: Default::default(),
+        }
+    }
+}
+
+impl<Id, Int, P> CostScaling<Id, Int, P> {
+    /// Reports the flow routed so far to `progress` before every refine phase, which can cancel
+    /// the run.
+    pub fn with_progress<Q>(self, progress: Q) -> CostScaling<Id, Int, Q> {
+        CostScaling {
+            progress,
+            cancelled: false,
+            iterations: 0,
+            _phantom: Default::default(),
+        }
+    }
+}
+
+impl<N, I, P> MinCostFlow for CostScaling<N, I, P>
+where
+    N: Id,
+    I: Int + CheckedAdd + CheckedMul + FromPrimitive,
+    P: Progress<I>,
+{
+    type NodeWeight = N;
+    type EdgeCapacity = I;
+    type EdgeCost = I;
+    type GraphIter = FlowGraph<N, I, I>;
+    type Error = ();
+    type Paths = FlowPaths<N, I>;
+
+    fn min_cost_flow(
+        &mut self,
+        graph_iter: &Self::GraphIter,
+    ) -> Result<(Self::EdgeCapacity, Self::Paths), Self::Error> {
+        let flow = self.edge_flows(graph_iter)?;
+        let max_flow = flow_value(&flow);
+        let paths = flow
+            .into_iter()
+            .filter_map(|((u, v), flow)| match (u.firm(), v.firm()) {
+                (Some(n1), Some(n2)) => Some(((n1.clone(), n2.clone()), flow)),
+                _ => None,
+            })
+            .fold(BTreeMap::new(), |mut acc, (pair, flow)| {
+                *acc.entry(pair).or_default() += flow;
+                acc
+            });
+
+        Ok((max_flow, paths))
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    fn iterations(&self) -> usize {
+        self.iterations
+    }
+}
+
+impl<N, I, P> CertifiedMinCostFlow for CostScaling<N, I, P>
+where
+    N: Id,
+    I: Int + CheckedAdd + CheckedMul + FromPrimitive,
+    P: Progress<I>,
+{
+    type Certificate = Certificate<N, I>;
+
+    /// The prices of the cost-scaling method are only approximately optimal, so the potentials of
+    /// the certificate are computed from the flow (see `Certificate::from_flow`).
+    fn certified_min_cost_flow(
+        &mut self,
+        graph_iter: &Self::GraphIter,
+    ) -> Result<(Self::EdgeCapacity, Self::Certificate), Self::Error> {
+        let flow = self.edge_flows(graph_iter)?;
+        let max_flow = flow_value(&flow);
+        let certificate = Certificate::from_flow(graph_iter, flow).ok_or(())?;
+
+        Ok((max_flow, certificate))
+    }
+}
+
+impl<N, I, P> CostScaling<N, I, P>
+where
+    N: Id,
+    I: Int + CheckedAdd + CheckedMul + FromPrimitive,
+    P: Progress<I>,
+{
+    // Compute a min-cost max flow and return the flow over every edge with a positive flow
+    fn edge_flows(&mut self, graph_iter: &FlowGraph<N, I, I>) -> Result<EdgeFlows<N, I>, ()> {
+        self.cancelled = false;
+        self.iterations = 0;
+        let mut nodes = BTreeMap::new();
+        for (u, v) in graph_iter.keys() {
+            let len = nodes.len();
+            nodes.entry(u.clone()).or_insert(len);
+            let len = nodes.len();
+            nodes.entry(v.clone()).or_insert(len);
+        }
+        let (source, sink) = match (nodes.get(&Node::Source), nodes.get(&Node::Sink)) {
+            (Some(source), Some(sink)) => (*source, *sink),
+            _ => return Ok(BTreeMap::new()),
+        };
+
+        let mut edges: Vec<_> = graph_iter
+            .iter()
+            .map(|((u, v), e)| (nodes[u], nodes[v], max(e.capacity, I::zero()), e.cost))
+            .collect();
+
+        // the return edge must be more expensive than any path is cheap
+        let supply: I = edges
+            .iter()
+            .filter(|(u, _, _, _)| *u == source)
+            .map(|(_, _, capacity, _)| *capacity)
+            .sum();
+        let big_m = edges
+            .iter()
+            .map(|(_, _, _, cost)| max(*cost, -*cost))
+            .try_fold(I::one(), |acc, cost| acc.checked_add(&cost))
+            .ok_or(())?;
+        edges.push((sink, source, supply, -big_m));
+
+        let mut state = State::new(nodes.len(), &edges)?;
+        let return_arc = state.graph.edge_arcs[edges.len() - 1];
+        let (phases, cancelled) = state.run(return_arc, &mut self.progress);
+        self.iterations = phases;
+        self.cancelled = cancelled;
+
+        // the circulation is feasible, but without the return edge it might not be a max flow
+        if cancelled {
+            let rev = state.graph.rev[return_arc];
+            state.graph.residual[return_arc] = I::zero();
+            state.graph.residual[rev] = I::zero();
+            state
+                .graph
+                .complete(source, sink, &mut PushRelabel)
+                .map_err(|_| ())?;
+        }
+
+        Ok(graph_iter
+            .keys()
+            .zip(state.graph.flows())
+            .filter(|(_, flow)| *flow > I::zero())
+            .map(|(edge, flow)| (edge.clone(), flow))
+            .collect())
+    }
+}
+
+// The flow out of the source
+fn flow_value<N, I>(flow: &EdgeFlows<N, I>) -> I
+where
+    N: Id,
+    I: Int,
+{
+    flow.iter()
+        .filter(|((u, _), _)| *u == Node::Source)
+        .map(|(_, flow)| *flow)
+        .sum()
+}
+
+/// The state of the cost-scaling method, i.e. a pseudoflow in the residual graph together with
+/// the prices of the nodes.
+struct State<I> {
+    graph: Residual<I>,
+    excess: Vec<I>,
+    price: Vec<I>,
+    current: Vec<usize>,
+    active: VecDeque<usize>,
+    max_cost: I,
+    alpha: I,
+}
+
+impl<I> State<I>
+where
+    I: Int + CheckedMul + FromPrimitive,
+{
+    fn new(n: usize, edges: &[(usize, usize, I, I)]) -> Result<Self, ()> {
+        let scale = I::from_usize(n + 1).ok_or(())?;
+        let alpha = I::from_u8(ALPHA).ok_or(())?;
+
+        let edges = edges
+            .iter()
+            .map(|(u, v, capacity, c)| Ok((*u, *v, *capacity, c.checked_mul(&scale).ok_or(())?)))
+            .collect::<Result<Vec<_>, ()>>()?;
+        let max_cost = edges
+            .iter()
+            .map(|(_, _, _, c)| max(*c, -*c))
+            .max()
+            .unwrap_or_else(I::zero);
+
+        // leave room for the prices
+        let headroom = I::from_usize(4 * (n + 1)).ok_or(())?;
+        max_cost.checked_mul(&headroom).ok_or(())?;
+
+        let graph = Residual::new(n, &edges);
+        Ok(Self {
+            current: graph.first.clone(),
+            graph,
+            excess: vec![I::zero(); n],
+            price: vec![I::zero(); n],
+            active: VecDeque::new(),
+            max_cost,
+            alpha,
+        })
+    }
+
+    fn reduced_cost(&self, u: usize, arc: usize) -> I {
+        self.graph.reduced_cost(u, arc, &self.price)
+    }
+
+    fn push(&mut self, u: usize, arc: usize, amount: I) {
+        let v = self.graph.head[arc];
+        self.graph.augment(arc, amount);
+        self.excess[u] -= amount;
+        if self.excess[v] <= I::zero() && self.excess[v] + amount > I::zero() {
+            self.active.push_back(v);
+        }
+        self.excess[v] += amount;
+    }
+
+    fn relabel(&mut self, u: usize, eps: I) {
+        let new_price = (self.graph.first[u]..self.graph.first[u + 1])
+            .filter(|arc| self.graph.residual[*arc] > I::zero())
+            .map(|arc| self.price[self.graph.head[arc]] - self.graph.cost[arc])
+            .max()
+            .expect("bug: tried to relabel a node with no residual arcs");
+        self.price[u] = new_price - eps;
+    }
+
+    // Keep pushing excess flow along admissible arcs until we can't any more.
+    fn discharge(&mut self, u: usize, eps: I) {
+        while self.excess[u] > I::zero() {
+            let arc = self.current[u];
+            if arc == self.graph.first[u + 1] {
+                self.relabel(u, eps);
+                self.current[u] = self.graph.first[u];
+            } else if self.graph.residual[arc] > I::zero() && self.reduced_cost(u, arc) < I::zero()
+            {
+                let amount = min(self.excess[u], self.graph.residual[arc]);
+                self.push(u, arc, amount);
+            } else {
+                self.current[u] += 1;
+            }
+        }
+    }
+
+    /// Turn an `alpha * eps`-optimal circulation into an `eps`-optimal one.
+    fn refine(&mut self, eps: I) {
+        // saturate every arc with a negative reduced cost, which makes the pseudoflow 0-optimal
+        for u in 0..self.excess.len() {
+            for arc in self.graph.first[u]..self.graph.first[u + 1] {
+                let residual = self.graph.residual[arc];
+                if residual > I::zero() && self.reduced_cost(u, arc) < I::zero() {
+                    self.push(u, arc, residual);
+                }
+            }
+            self.current[u] = self.graph.first[u];
+        }
+
+        while let Some(u) = self.active.pop_front() {
+            self.discharge(u, eps);
+        }
+    }
+
+    /// Refine the circulation until it's optimal, reporting the flow over the `return_arc` to
+    /// `progress` before every refine phase. Returns the number of refine phases and whether
+    /// `progress` cancelled the run.
+    fn run<P: Progress<I>>(&mut self, return_arc: usize, progress: &mut P) -> (usize, bool) {
+        let mut phases = 0;
+        let mut eps = self.max_cost;
+        while eps > I::one() {
+            let flow = self.graph.residual[self.graph.rev[return_arc]];
+            if !progress.report(phases, flow) {
+                return (phases, true);
+            }
+            eps = max(eps / self.alpha, I::one());
+            self.refine(eps);
+            phases += 1;
+        }
+        (phases, false)
+    }
+}
@@ -1,10 +1,35 @@
 #[cfg(feature = "lemon-cpp")]
 pub mod network_simplex;
 
+pub mod certificate;
+pub mod cost_scaling;
+pub mod native_simplex;
 pub mod primal_dual;
+pub mod progress;
 
+use alloc::collections::BTreeMap;
 use core::fmt::Debug;
 
+use crate::node::Node;
+
+/// The weight of an edge in the flow network, i.e. its per-unit cost and its capacity.
+#[derive(Debug, Clone, Default)]
+pub struct EdgeWeight<Cost, Capacity> {
+    pub cost: Cost,         // c
+    pub capacity: Capacity, // µ
+}
+
+/// A flow network, i.e. a map of (directed) edges to their weights. A min-cost flow needs both the
+/// cost and the capacity of every edge, so the weights are `EdgeWeight`s rather than capacities.
+pub type FlowGraph<N, Cost, Capacity> = BTreeMap<(Node<N>, Node<N>), EdgeWeight<Cost, Capacity>>;
+
+/// The flow between every pair of firms, i.e. over the edges between nodes with IDs (summed over
+/// all the nodes of a firm, see `Node::firm`).
+pub type FlowPaths<N, Capacity> = BTreeMap<(N, N), Capacity>;
+
+/// The flow over edges between nodes (including the source and the sink).
+pub type EdgeFlows<N, Capacity> = BTreeMap<(Node<N>, Node<N>), Capacity>;
+
 /// The minimum cost max flow algorithm.
 pub trait MinCostFlow {
     type NodeWeight;
@@ -20,4 +45,123 @@ pub trait MinCostFlow {
         &mut self,
         graph_iter: &Self::GraphIter,
     ) -> Result<(Self::EdgeCapacity, Self::Paths), Self::Error>;
+
+    /// Checks if the last run was cancelled (see `Progress`), in which case its flow is a max flow
+    /// but might not be of minimum cost.
+    fn cancelled(&self) -> bool {
+        false
+    }
+
+    /// The number of iterations of the last run (the same iterations that are reported to
+    /// `Progress`), or zero if the algorithm doesn't count them.
+    fn iterations(&self) -> usize {
+        0
+    }
+}
+
+/// A min-cost flow algorithm that can also emit a certificate (see `Certificate`), which proves
+/// that its result is optimal.
+pub trait CertifiedMinCostFlow: MinCostFlow {
+    type Certificate;
+
+    /// Same as `min_cost_flow`, but returns the certificate of the result instead of the paths
+    /// (the certificate contains the flow over every edge).
+    fn certified_min_cost_flow(
+        &mut self,
+        graph_iter: &Self::GraphIter,
+    ) -> Result<(Self::EdgeCapacity, Self::Certificate), Self::Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::mcmf::cost_scaling::CostScaling;
+    use crate::algo::mcmf::native_simplex::NativeSimplex;
+    use crate::algo::mcmf::primal_dual::PrimalDual;
+    use crate::testing::Rng;
+
+    type Graph = FlowGraph<u64, i64, i64>;
+
+    // Small pseudo-random obligation networks over the firms `0..5`, where the firms `0` and `1`
+    // are supplied by the source and the firms `3` and `4` supply the sink
+    fn graphs() -> impl Iterator<Item = Graph> {
+        let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+        let mut next = move |n: u64| rng.below(n);
+        (0..200).map(move |_| {
+            let mut g = Graph::new();
+            for firm in [0, 1] {
+                g.insert(
+                    (Node::Source, Node::WithId(firm)),
+                    EdgeWeight {
+                        cost: next(3) as i64,
+                        capacity: next(20) as i64,
+                    },
+                );
+            }
+            for firm in [3, 4] {
+                g.insert(
+                    (Node::WithId(firm), Node::Sink),
+                    EdgeWeight {
+                        cost: next(3) as i64,
+                        capacity: next(20) as i64,
+                    },
+                );
+            }
+            for _ in 0..next(12) {
+                let (u, v) = (next(5), next(5));
+                if u != v {
+                    g.insert(
+                        (Node::WithId(u), Node::WithId(v)),
+                        EdgeWeight {
+                            cost: next(6) as i64,
+                            capacity: next(10) as i64,
+                        },
+                    );
+                }
+            }
+            g
+        })
+    }
+
+    // The cost of the flow `paths` after checking that it's a feasible flow of the value `flow`
+    fn cost(g: &Graph, flow: i64, paths: &FlowPaths<u64, i64>) -> i64 {
+        let mut cost = 0;
+        let mut balance = BTreeMap::new();
+        for ((u, v), f) in paths {
+            let edge = &g[&(Node::WithId(*u), Node::WithId(*v))];
+            assert!(*f > 0 && *f <= edge.capacity);
+            cost += f * edge.cost;
+            *balance.entry(*u).or_insert(0) += f;
+            *balance.entry(*v).or_insert(0) -= f;
+        }
+
+        let mut supplied = 0;
+        for (firm, b) in balance {
+            let edge = match (firm, b) {
+                (_, 0) => continue,
+                (0 | 1, 1..) => &g[&(Node::Source, Node::WithId(firm))],
+                (3 | 4, ..=-1) => &g[&(Node::WithId(firm), Node::Sink)],
+                _ => panic!("flow isn't conserved at {firm}"),
+            };
+            assert!(b.abs() <= edge.capacity);
+            cost += b.abs() * edge.cost;
+            supplied += b.max(0);
+        }
+        assert_eq!(supplied, flow);
+        cost
+    }
+
+    #[test]
+    fn min_cost_flow_algos_agree() {
+        for g in graphs() {
+            let (flow, paths) = PrimalDual::default().min_cost_flow(&g).unwrap();
+            let expected = (flow, cost(&g, flow, &paths));
+
+            let (flow, paths) = CostScaling::default().min_cost_flow(&g).unwrap();
+            assert_eq!((flow, cost(&g, flow, &paths)), expected);
+
+            let (flow, paths) = NativeSimplex::default().min_cost_flow(&g).unwrap();
+            assert_eq!((flow, cost(&g, flow, &paths)), expected);
+        }
+    }
 }
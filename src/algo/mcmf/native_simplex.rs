@@ -0,0 +1,579 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use num_traits::CheckedAdd;
+
+use crate::algo::max_flow::{MaxFlowError, PushRelabel};
+use crate::algo::mcmf::certificate::Certificate;
+use crate::algo::mcmf::progress::{NoProgress, Progress};
+use crate::algo::mcmf::{CertifiedMinCostFlow, FlowGraph, FlowPaths, MinCostFlow};
+use crate::algo::residual::Residual;
+use crate::id::Id;
+use crate::int::Int;
+use crate::node::Node;
+
+/// A pure-Rust min-cost flow impl using the (primal) network simplex method.
+///
+/// Unlike the LEMON based `NetworkSimplex`, this works with any `Id` and `Int`. Among all maximum
+/// flows the one with the smallest total cost is returned.
+///
+/// The node potentials can grow to a few times the sum of all absolute edge costs, so an error is
+/// returned if four times that sum doesn't fit in `Int`.
+///
+/// The `progress` observer is called at every pivot and can cancel the run, in which case the flow
+/// that didn't reach the sink is returned to the source and the rest is completed to a max flow by
+/// `PushRelabel` regardless of the costs (see `MinCostFlow::cancelled`).
+#[derive(Clone)]
+pub struct NativeSimplex<Id, Int, P = NoProgress> {
+    progress: P,
+    cancelled: bool,
+    /// The number of pivots of the last run
+    iterations: usize,
+    _phantom: PhantomData<(Id, Int)>,
+}
+
+impl<Id, Int> Default for NativeSimplex<Id, Int> {
+    fn default() -> Self {
+        Self {
+            progress: NoProgress,
+            cancelled: false,
+            iterations: 0,
+            _phantom: Default::default(),
+        }
+    }
+}
+
+impl<Id, Int, P> NativeSimplex<Id, Int, P> {
+    /// Reports the flow that reaches the sink to `progress` at every pivot, which can cancel the
+    /// run.
+    pub fn with_progress<Q>(self, progress: Q) -> NativeSimplex<Id, Int, Q> {
+        NativeSimplex {
+            progress,
+            cancelled: false,
+            iterations: 0,
+            _phantom: Default::default(),
+        }
+    }
+}
+
+impl<N, I, P> MinCostFlow for NativeSimplex<N, I, P>
+where
+    N: Id,
+    I: Int + CheckedAdd,
+    P: Progress<I>,
+{
+    type NodeWeight = N;
+    type EdgeCapacity = I;
+    type EdgeCost = I;
+    type GraphIter = FlowGraph<N, I, I>;
+    type Error = ();
+    type Paths = FlowPaths<N, I>;
+
+    fn min_cost_flow(
+        &mut self,
+        graph_iter: &Self::GraphIter,
+    ) -> Result<(Self::EdgeCapacity, Self::Paths), Self::Error> {
+        let nodes = node_indices(graph_iter);
+        let simplex = match self.solve(graph_iter, &nodes)? {
+            Some(simplex) => simplex,
+            None => return Ok((I::zero(), BTreeMap::new())),
+        };
+
+        let ids: BTreeMap<_, _> = nodes.into_iter().map(|(node, idx)| (idx, node)).collect();
+        let mut max_flow = I::zero();
+        let mut paths = BTreeMap::new();
+        for arc in &simplex.arcs[..simplex.real_arcs] {
+            if arc.flow <= I::zero() {
+                continue;
+            }
+            if ids[&arc.from] == Node::Source {
+                max_flow += arc.flow;
+            }
+            if let (Some(n1), Some(n2)) = (ids[&arc.from].firm(), ids[&arc.to].firm()) {
+                *paths.entry((n1.clone(), n2.clone())).or_default() += arc.flow;
+            }
+        }
+
+        Ok((max_flow, paths))
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    fn iterations(&self) -> usize {
+        self.iterations
+    }
+}
+
+impl<N, I, P> CertifiedMinCostFlow for NativeSimplex<N, I, P>
+where
+    N: Id,
+    I: Int + CheckedAdd,
+    P: Progress<I>,
+{
+    type Certificate = Certificate<N, I>;
+
+    /// The potentials of the final spanning tree satisfy the optimality conditions on all the
+    /// (real) arcs, so they prove the optimality of the flow (unless the run was cancelled).
+    fn certified_min_cost_flow(
+        &mut self,
+        graph_iter: &Self::GraphIter,
+    ) -> Result<(Self::EdgeCapacity, Self::Certificate), Self::Error> {
+        let nodes = node_indices(graph_iter);
+        let simplex = match self.solve(graph_iter, &nodes)? {
+            Some(simplex) => simplex,
+            None => {
+                let certificate = Certificate::from_flow(graph_iter, BTreeMap::new()).ok_or(())?;
+                return Ok((I::zero(), certificate));
+            }
+        };
+
+        let mut max_flow = I::zero();
+        let mut flow = BTreeMap::new();
+        for (edge, arc) in graph_iter.keys().zip(&simplex.arcs[..simplex.real_arcs]) {
+            if arc.flow <= I::zero() {
+                continue;
+            }
+            if edge.0 == Node::Source {
+                max_flow += arc.flow;
+            }
+            flow.insert(edge.clone(), arc.flow);
+        }
+        let potentials = nodes
+            .into_iter()
+            .map(|(node, idx)| (node, simplex.potential[idx]))
+            .collect();
+
+        Ok((
+            max_flow,
+            Certificate::with_potentials(graph_iter, flow, potentials),
+        ))
+    }
+}
+
+// Number the nodes of the graph
+fn node_indices<N, I>(graph_iter: &FlowGraph<N, I, I>) -> BTreeMap<Node<N>, usize>
+where
+    N: Id,
+{
+    let mut nodes = BTreeMap::new();
+    for (u, v) in graph_iter.keys() {
+        let len = nodes.len();
+        nodes.entry(u.clone()).or_insert(len);
+        let len = nodes.len();
+        nodes.entry(v.clone()).or_insert(len);
+    }
+    nodes
+}
+
+impl<N, I, P> NativeSimplex<N, I, P>
+where
+    N: Id,
+    I: Int + CheckedAdd,
+    P: Progress<I>,
+{
+    // Run the network simplex method over the graph, if it has a source and a sink. Fails if the
+    // costs are too large.
+    fn solve(
+        &mut self,
+        graph_iter: &FlowGraph<N, I, I>,
+        nodes: &BTreeMap<Node<N>, usize>,
+    ) -> Result<Option<Simplex<I>>, ()> {
+        self.cancelled = false;
+        self.iterations = 0;
+        let (source, sink) = match (nodes.get(&Node::Source), nodes.get(&Node::Sink)) {
+            (Some(source), Some(sink)) => (*source, *sink),
+            _ => return Ok(None),
+        };
+
+        // big-M exceeds the cost of any simple path over real arcs, and the potentials stay within
+        // a few times big-M
+        let big_m = graph_iter
+            .values()
+            .map(|e| core::cmp::max(e.cost, -e.cost))
+            .try_fold(I::one(), |acc, cost| acc.checked_add(&cost))
+            .ok_or(())?;
+        let double = big_m.checked_add(&big_m).ok_or(())?;
+        double.checked_add(&double).ok_or(())?;
+
+        let arcs = graph_iter
+            .iter()
+            .map(|((u, v), e)| Edge::new(nodes[u], nodes[v], e.capacity, e.cost))
+            .collect();
+        let supply: I = graph_iter
+            .iter()
+            .filter(|((u, _), _)| u == &Node::Source)
+            .map(|(_, e)| core::cmp::max(e.capacity, I::zero()))
+            .sum();
+
+        let mut simplex = Simplex::new(nodes.len(), arcs, source, sink, supply, big_m);
+        let (pivots, cancelled) = simplex.run(sink, supply, &mut self.progress);
+        self.iterations = pivots;
+        self.cancelled = cancelled;
+        if cancelled {
+            simplex.repair(source, sink).map_err(|_| ())?;
+        }
+        Ok(Some(simplex))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Edge<I> {
+    from: usize,
+    to: usize,
+    capacity: I,
+    cost: I,
+    flow: I,
+}
+
+impl<I: Int> Edge<I> {
+    fn new(from: usize, to: usize, capacity: I, cost: I) -> Self {
+        Self {
+            from,
+            to,
+            capacity: core::cmp::max(capacity, I::zero()),
+            cost,
+            flow: I::zero(),
+        }
+    }
+}
+
+/// The spanning tree structure of the network simplex method.
+///
+/// An artificial root node is connected to every node by a big-M cost arc, which gives a trivially
+/// feasible (and strongly feasible) initial basis. Flow that cannot be routed from source to sink
+/// stays on the artificial arcs, which makes the method return a maximum flow of minimum cost.
+struct Simplex<I> {
+    arcs: Vec<Edge<I>>,
+    real_arcs: usize,
+    parent: Vec<usize>,
+    pred: Vec<usize>,
+    depth: Vec<usize>,
+    children: Vec<Vec<usize>>,
+    potential: Vec<I>,
+    in_tree: Vec<bool>,
+    next_arc: usize,
+}
+
+impl<I: Int> Simplex<I> {
+    fn new(
+        n: usize,
+        mut arcs: Vec<Edge<I>>,
+        source: usize,
+        sink: usize,
+        supply: I,
+        big_m: I,
+    ) -> Self {
+        let real_arcs = arcs.len();
+        let root = n;
+
+        let parent = vec![root; n + 1];
+        let mut pred = vec![0; n + 1];
+        let mut depth = vec![1; n + 1];
+        let mut children = vec![vec![]; n + 1];
+        let mut potential = vec![I::zero(); n + 1];
+        let mut in_tree = vec![false; real_arcs];
+
+        depth[root] = 0;
+        for node in 0..n {
+            let arc = if node == sink {
+                let mut arc = Edge::new(root, node, supply, big_m);
+                arc.flow = supply;
+                potential[node] = big_m;
+                arc
+            } else {
+                let mut arc = Edge::new(node, root, supply, big_m);
+                if node == source {
+                    arc.flow = supply;
+                }
+                potential[node] = -big_m;
+                arc
+            };
+            pred[node] = arcs.len();
+            children[root].push(node);
+            arcs.push(arc);
+            in_tree.push(true);
+        }
+
+        Self {
+            arcs,
+            real_arcs,
+            parent,
+            pred,
+            depth,
+            children,
+            potential,
+            in_tree,
+            next_arc: 0,
+        }
+    }
+
+    fn reduced_cost(&self, arc: usize) -> I {
+        let a = &self.arcs[arc];
+        a.cost + self.potential[a.from] - self.potential[a.to]
+    }
+
+    /// Returns the magnitude by which `arc` violates the optimality conditions, if it does.
+    fn violation(&self, arc: usize) -> Option<I> {
+        if self.in_tree[arc] {
+            return None;
+        }
+        let a = &self.arcs[arc];
+        let rc = self.reduced_cost(arc);
+        if a.flow < a.capacity && rc < I::zero() {
+            Some(-rc)
+        } else if a.flow > I::zero() && rc > I::zero() {
+            Some(rc)
+        } else {
+            None
+        }
+    }
+
+    /// Block search pivot rule: pick the most violating arc from the next block of arcs.
+    fn find_entering_arc(&mut self) -> Option<usize> {
+        let m = self.arcs.len();
+        let mut block = 1;
+        while block * block < m {
+            block += 1;
+        }
+
+        let mut best: Option<(usize, I)> = None;
+        let mut scanned = 0;
+        while scanned < m {
+            let arc = self.next_arc;
+            self.next_arc = (self.next_arc + 1) % m;
+            scanned += 1;
+
+            if let Some(violation) = self.violation(arc) {
+                match best {
+                    Some((_, v)) if v >= violation => {}
+                    _ => best = Some((arc, violation)),
+                }
+            }
+            if scanned % block == 0 && best.is_some() {
+                break;
+            }
+        }
+        best.map(|(arc, _)| arc)
+    }
+
+    /// Residual capacity of the tree arc `pred[node]` when pushing flow towards `node`'s parent
+    /// (`upwards == true`) or away from it.
+    fn residual(&self, node: usize, upwards: bool) -> I {
+        let a = &self.arcs[self.pred[node]];
+        if (a.from == node) == upwards {
+            a.capacity - a.flow
+        } else {
+            a.flow
+        }
+    }
+
+    fn augment(&mut self, node: usize, upwards: bool, delta: I) {
+        let arc = self.pred[node];
+        let a = &mut self.arcs[arc];
+        if (a.from == node) == upwards {
+            a.flow += delta;
+        } else {
+            a.flow -= delta;
+        }
+    }
+
+    fn apex(&self, mut u: usize, mut v: usize) -> usize {
+        while u != v {
+            if self.depth[u] >= self.depth[v] {
+                u = self.parent[u];
+            } else {
+                v = self.parent[v];
+            }
+        }
+        u
+    }
+
+    fn pivot(&mut self, entering: usize) {
+        let e = &self.arcs[entering];
+        // orient the cycle along the direction of the flow change on the entering arc
+        let (first, second) = if e.flow == I::zero() {
+            (e.from, e.to)
+        } else {
+            (e.to, e.from)
+        };
+        let apex = self.apex(first, second);
+
+        // Find the leaving arc, choosing the last blocking arc along the cycle orientation
+        // (starting from the apex) to keep the tree strongly feasible.
+        let mut delta = self.arcs[entering].capacity;
+        let mut leaving = None;
+
+        let mut node = first;
+        while node != apex {
+            let r = self.residual(node, false);
+            if r < delta {
+                delta = r;
+                leaving = Some((node, true));
+            }
+            node = self.parent[node];
+        }
+        if self.arcs[entering].capacity <= delta {
+            delta = self.arcs[entering].capacity;
+            leaving = None;
+        }
+        let mut node = second;
+        while node != apex {
+            let r = self.residual(node, true);
+            if r <= delta {
+                delta = r;
+                leaving = Some((node, false));
+            }
+            node = self.parent[node];
+        }
+
+        // push flow around the cycle
+        if delta > I::zero() {
+            let mut node = first;
+            while node != apex {
+                self.augment(node, false, delta);
+                node = self.parent[node];
+            }
+            let mut node = second;
+            while node != apex {
+                self.augment(node, true, delta);
+                node = self.parent[node];
+            }
+            let e = &mut self.arcs[entering];
+            if first == e.from {
+                e.flow += delta;
+            } else {
+                e.flow -= delta;
+            }
+        }
+
+        if let Some((out_node, on_first_side)) = leaving {
+            let (u_in, v_in) = if on_first_side {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            self.update_tree(entering, u_in, v_in, out_node);
+        }
+    }
+
+    /// Replace the tree arc above `out_node` with `entering`, which connects `u_in` (on the same
+    /// side of the cycle as `out_node`) to `v_in`.
+    fn update_tree(&mut self, entering: usize, u_in: usize, v_in: usize, out_node: usize) {
+        let leaving = self.pred[out_node];
+        self.in_tree[leaving] = false;
+        self.in_tree[entering] = true;
+
+        // reverse the parent pointers on the path from `u_in` up to `out_node`
+        let mut node = u_in;
+        let mut new_parent = v_in;
+        let mut new_pred = entering;
+        loop {
+            let old_parent = self.parent[node];
+            let old_pred = self.pred[node];
+
+            self.detach(old_parent, node);
+            self.parent[node] = new_parent;
+            self.pred[node] = new_pred;
+            self.children[new_parent].push(node);
+
+            if node == out_node {
+                break;
+            }
+            new_parent = node;
+            new_pred = old_pred;
+            node = old_parent;
+        }
+
+        // update depths and potentials of the re-hung subtree
+        let mut stack = vec![u_in];
+        while let Some(node) = stack.pop() {
+            let p = self.parent[node];
+            let a = &self.arcs[self.pred[node]];
+            self.depth[node] = self.depth[p] + 1;
+            self.potential[node] = if a.from == p {
+                self.potential[p] + a.cost
+            } else {
+                self.potential[p] - a.cost
+            };
+            stack.extend(self.children[node].iter().copied());
+        }
+    }
+
+    fn detach(&mut self, parent: usize, child: usize) {
+        let children = &mut self.children[parent];
+        if let Some(pos) = children.iter().position(|c| *c == child) {
+            children.swap_remove(pos);
+        }
+    }
+
+    /// Pivot until the tree is optimal, reporting the flow that reaches the `sink` over the real
+    /// arcs to `progress` at every pivot. Returns the number of pivots and whether `progress`
+    /// cancelled the run.
+    fn run<P: Progress<I>>(&mut self, sink: usize, supply: I, progress: &mut P) -> (usize, bool) {
+        let mut pivots = 0;
+        while let Some(entering) = self.find_entering_arc() {
+            // the rest of the supply reaches the sink over its artificial arc
+            let flow = supply - self.arcs[self.real_arcs + sink].flow;
+            if !progress.report(pivots, flow) {
+                return (pivots, true);
+            }
+            self.pivot(entering);
+            pivots += 1;
+        }
+        (pivots, false)
+    }
+
+    /// Turns the flow over the real arcs into a max flow from `source` to `sink` after the run was
+    /// cancelled. The artificial arcs carry the excess of the nodes that received more flow than
+    /// they passed on, so that flow is returned to the source first, and the flow is then completed
+    /// to a max flow regardless of the costs.
+    fn repair(&mut self, source: usize, sink: usize) -> Result<(), MaxFlowError>
+    where
+        I: CheckedAdd,
+    {
+        let root = self.potential.len() - 1;
+        let arcs = &mut self.arcs[..self.real_arcs];
+
+        let mut excess = vec![I::zero(); root];
+        for a in arcs.iter() {
+            excess[a.to] += a.flow;
+            excess[a.from] -= a.flow;
+        }
+
+        // return the excess from the root (as a super source) to the source over the reversed
+        // arcs that carry flow
+        let mut edges: Vec<_> = arcs
+            .iter()
+            .map(|a| (a.to, a.from, a.flow, I::zero()))
+            .collect();
+        edges.extend(
+            (0..root)
+                .filter(|v| *v != source && *v != sink && excess[*v] > I::zero())
+                .map(|v| (root, v, excess[v], I::zero())),
+        );
+        let mut graph = Residual::new(root + 1, &edges);
+        graph.complete(root, source, &mut PushRelabel)?;
+        for (a, returned) in arcs.iter_mut().zip(graph.flows()) {
+            a.flow -= returned;
+        }
+
+        // complete the rest to a max flow
+        let edges: Vec<_> = arcs
+            .iter()
+            .map(|a| (a.from, a.to, a.capacity, I::zero()))
+            .collect();
+        let mut graph = Residual::new(root, &edges);
+        for (edge, a) in arcs.iter().enumerate() {
+            graph.augment(graph.edge_arcs[edge], a.flow);
+        }
+        graph.complete(source, sink, &mut PushRelabel)?;
+        for (a, flow) in arcs.iter_mut().zip(graph.flows()) {
+            a.flow = flow;
+        }
+        Ok(())
+    }
+}
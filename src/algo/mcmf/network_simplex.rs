@@ -2,21 +2,28 @@ use alloc::collections::BTreeMap;
 
 use mcmf::{Capacity, Cost, GraphBuilder, Vertex};
 
-use crate::algo::mcmf::MinCostFlow;
+use crate::algo::mcmf::{EdgeWeight, FlowGraph, MinCostFlow};
 use crate::node::Node;
 
 pub type NodeWeight = i32;
 pub type EdgeCapacity = i32;
+pub type EdgeCost = i32;
 
 /// A min-cost flow impl using the network simplex method.
 pub struct NetworkSimplex;
 
-impl From<Node<NodeWeight>> for Vertex<NodeWeight> {
-    fn from(value: Node<NodeWeight>) -> Self {
+impl TryFrom<Node<NodeWeight>> for Vertex<NodeWeight> {
+    type Error = ();
+
+    fn try_from(value: Node<NodeWeight>) -> Result<Self, Self::Error> {
         match value {
-            Node::Source => Vertex::Source,
-            Node::Sink => Vertex::Sink,
-            Node::WithId(id) => Vertex::Node(id),
+            Node::Source => Ok(Vertex::Source),
+            Node::Sink => Ok(Vertex::Sink),
+            Node::WithId(id) => Ok(Vertex::Node(id)),
+            // LEMON only supports a single source and sink, and one node per firm
+            Node::Liquidity | Node::Outgoing(_) | Node::Incoming(_) | Node::PriorityClass(..) => {
+                Err(())
+            }
         }
     }
 }
@@ -24,8 +31,8 @@ impl From<Node<NodeWeight>> for Vertex<NodeWeight> {
 impl MinCostFlow for NetworkSimplex {
     type NodeWeight = NodeWeight;
     type EdgeCapacity = EdgeCapacity;
-    type EdgeCost = ();
-    type GraphIter = BTreeMap<(Node<NodeWeight>, Node<NodeWeight>), EdgeCapacity>;
+    type EdgeCost = EdgeCost;
+    type GraphIter = FlowGraph<NodeWeight, EdgeCost, EdgeCapacity>;
     type Error = ();
     type Paths = BTreeMap<(NodeWeight, NodeWeight), EdgeCapacity>;
 
@@ -34,18 +41,18 @@ impl MinCostFlow for NetworkSimplex {
         graph_iter: &Self::GraphIter,
     ) -> Result<(Self::EdgeCapacity, Self::Paths), Self::Error> {
         // build a graph from given obligation network
-        let g = graph_iter.iter().fold(
+        let g = graph_iter.iter().try_fold(
             GraphBuilder::new(),
-            |mut acc, ((debtor, creditor), amount)| {
+            |mut acc, ((debtor, creditor), EdgeWeight { cost, capacity })| {
                 acc.add_edge(
-                    Vertex::<NodeWeight>::from(*debtor),
-                    Vertex::<NodeWeight>::from(*creditor),
-                    Capacity(*amount),
-                    Cost(1),
+                    Vertex::<NodeWeight>::try_from(*debtor)?,
+                    Vertex::<NodeWeight>::try_from(*creditor)?,
+                    Capacity(*capacity),
+                    Cost(*cost),
                 );
-                acc
+                Ok::<_, ()>(acc)
             },
-        );
+        )?;
 
         // Get the minimum cost maximum flow paths and calculate "nid"
         let (max_flow, paths) = g.mcmf();
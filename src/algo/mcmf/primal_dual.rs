@@ -1,120 +1,377 @@
-use alloc::collections::BTreeMap;
-use core::fmt::Debug;
-use core::hash::Hash;
+use alloc::collections::{BTreeMap, BinaryHeap};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{min, Reverse};
 use core::marker::PhantomData;
 
 use num_traits::CheckedAdd;
-use petgraph::algo::dijkstra;
 use petgraph::graphmap::DiGraphMap;
-use petgraph::visit::EdgeFiltered;
-use petgraph::Direction;
 
-use crate::algo::max_flow::push_relabel_max_flow;
-use crate::algo::mcmf::MinCostFlow;
+use crate::algo::max_flow::{MaxFlow, MaxFlowError, PushRelabel};
+use crate::algo::mcmf::certificate::Certificate;
+use crate::algo::mcmf::progress::{NoProgress, Progress};
+use crate::algo::mcmf::{CertifiedMinCostFlow, EdgeWeight, FlowGraph, FlowPaths, MinCostFlow};
+use crate::algo::residual::Residual;
 use crate::id::Id;
 use crate::int::Int;
 use crate::node::Node;
 
-#[derive(Debug, Clone, Default)]
-pub struct EdgeWeight<Cost, Capacity> {
-    pub cost: Cost,         // c
-    pub capacity: Capacity, // µ
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PrimalDualError {
+    /// The primal-dual method requires all edge costs to be non-negative
+    NegativeCost,
+    MaxFlow(MaxFlowError),
 }
 
-#[derive(Default, Clone)]
-pub struct PrimalDual<Id, Int>(PhantomData<(Id, Int)>);
+impl From<MaxFlowError> for PrimalDualError {
+    fn from(e: MaxFlowError) -> Self {
+        Self::MaxFlow(e)
+    }
+}
 
-impl<N, I> MinCostFlow for PrimalDual<N, I>
+/// A min-cost flow impl using the primal-dual method, which is generic over the max flow
+/// algorithm `MF` used to saturate the admissible graph in every iteration.
+///
+/// The `progress` observer is called at every iteration and can cancel the run, in which case the
+/// flow pushed so far is completed to a max flow by `MF` regardless of the costs. The result is
+/// then feasible, but might not be of minimum cost (see `MinCostFlow::cancelled`).
+#[derive(Clone)]
+pub struct PrimalDual<Id, Int, MF = PushRelabel, P = NoProgress> {
+    max_flow: MF,
+    progress: P,
+    cancelled: bool,
+    iterations: usize,
+    _phantom: PhantomData<(Id, Int)>,
+}
+
+impl<Id, Int> Default for PrimalDual<Id, Int> {
+    fn default() -> Self {
+        Self::with_max_flow(PushRelabel)
+    }
+}
+
+impl<Id, Int, MF> PrimalDual<Id, Int, MF> {
+    pub fn with_max_flow(max_flow: MF) -> Self {
+        Self {
+            max_flow,
+            progress: NoProgress,
+            cancelled: false,
+            iterations: 0,
+            _phantom: Default::default(),
+        }
+    }
+}
+
+impl<Id, Int, MF, P> PrimalDual<Id, Int, MF, P> {
+    /// Reports the flow pushed so far to `progress` at every iteration, which can cancel the run.
+    pub fn with_progress<Q>(self, progress: Q) -> PrimalDual<Id, Int, MF, Q> {
+        PrimalDual {
+            max_flow: self.max_flow,
+            progress,
+            cancelled: false,
+            iterations: 0,
+            _phantom: Default::default(),
+        }
+    }
+}
+
+impl<N, I, MF, P> MinCostFlow for PrimalDual<N, I, MF, P>
 where
-    N: Id + Copy + Hash,
+    N: Id,
     I: Int + CheckedAdd,
+    MF: MaxFlow,
+    P: Progress<I>,
 {
     type NodeWeight = N;
     type EdgeCapacity = I;
     type EdgeCost = I;
-    type GraphIter = BTreeMap<(Node<N>, Node<N>), I>;
-    type Error = ();
-    type Paths = BTreeMap<(N, N), I>;
+    type GraphIter = FlowGraph<N, I, I>;
+    type Error = PrimalDualError;
+    type Paths = FlowPaths<N, I>;
 
     fn min_cost_flow(
         &mut self,
         graph_iter: &Self::GraphIter,
     ) -> Result<(Self::EdgeCapacity, Self::Paths), Self::Error> {
-        Ok(mtcs_primal_dual::<N, I>(graph_iter))
+        let solution = primal_dual(graph_iter, &mut self.max_flow, &mut self.progress)?;
+        self.cancelled = solution.cancelled;
+        self.iterations = solution.iterations;
+        Ok((solution.max_flow, solution.paths()))
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancelled
     }
+
+    fn iterations(&self) -> usize {
+        self.iterations
+    }
+}
+
+/// The certificate of a cancelled run doesn't verify (unless the flow happens to be of minimum
+/// cost).
+impl<N, I, MF, P> CertifiedMinCostFlow for PrimalDual<N, I, MF, P>
+where
+    N: Id,
+    I: Int + CheckedAdd,
+    MF: MaxFlow,
+    P: Progress<I>,
+{
+    type Certificate = Certificate<N, I>;
+
+    fn certified_min_cost_flow(
+        &mut self,
+        graph_iter: &Self::GraphIter,
+    ) -> Result<(Self::EdgeCapacity, Self::Certificate), Self::Error> {
+        let solution = primal_dual(graph_iter, &mut self.max_flow, &mut self.progress)?;
+        self.cancelled = solution.cancelled;
+        self.iterations = solution.iterations;
+        Ok((solution.max_flow, solution.certificate(graph_iter)))
+    }
+}
+
+/// Computes a minimum-cost maximum flow from `Node::Source` to `Node::Sink` using the primal-dual
+/// method.
+///
+/// Node potentials are maintained such that the reduced cost of every residual edge stays
+/// non-negative, which allows shortest paths to be computed with Dijkstra's algorithm. In every
+/// iteration the potentials are raised by the shortest path distances and a max flow is pushed
+/// through the admissible graph, i.e. the residual edges with zero reduced cost.
+///
+/// All edge costs must be non-negative.
+pub fn mtcs_primal_dual<N, I, MF>(
+    obligation_list: &FlowGraph<N, I, I>,
+    max_flow_algo: &mut MF,
+) -> Result<(I, FlowPaths<N, I>), PrimalDualError>
+where
+    N: Id,
+    I: Int + CheckedAdd,
+    MF: MaxFlow,
+{
+    let solution = primal_dual(obligation_list, max_flow_algo, &mut NoProgress)?;
+    Ok((solution.max_flow, solution.paths()))
 }
 
-pub fn mtcs_primal_dual<N, I>(
-    obligation_list: &BTreeMap<(Node<N>, Node<N>), I>,
-) -> (I, BTreeMap<(N, N), I>)
+/// The state of the primal-dual method once it has finished.
+struct Solution<N, I> {
+    nodes: BTreeMap<Node<N>, usize>,
+    edges: Vec<(usize, usize, I, I)>,
+    graph: Residual<I>,
+    potential: Vec<I>,
+    max_flow: I,
+    /// Whether the run was cancelled by the progress observer
+    cancelled: bool,
+    /// The number of augmenting iterations
+    iterations: usize,
+}
+
+fn primal_dual<N, I, MF, P>(
+    obligation_list: &FlowGraph<N, I, I>,
+    max_flow_algo: &mut MF,
+    progress: &mut P,
+) -> Result<Solution<N, I>, PrimalDualError>
 where
-    N: Id + Copy + Hash,
+    N: Id,
     I: Int + CheckedAdd,
+    MF: MaxFlow,
+    P: Progress<I>,
 {
-    let mut graph = DiGraphMap::from_edges(obligation_list.iter().map(|((d, c), capacity)| {
-        (
-            *d,
-            *c,
-            EdgeWeight {
-                capacity: *capacity,
-                cost: I::zero(),
-            },
-        )
-    }));
+    if obligation_list.values().any(|e| e.cost < I::zero()) {
+        return Err(PrimalDualError::NegativeCost);
+    }
 
+    let mut nodes = BTreeMap::new();
+    for (u, v) in obligation_list.keys() {
+        let len = nodes.len();
+        nodes.entry(u.clone()).or_insert(len);
+        let len = nodes.len();
+        nodes.entry(v.clone()).or_insert(len);
+    }
+    let edges: Vec<_> = obligation_list
+        .iter()
+        .map(|((u, v), e)| (nodes[u], nodes[v], e.capacity, e.cost))
+        .collect();
+    let mut graph = Residual::new(nodes.len(), &edges);
+    let mut potential = vec![I::zero(); nodes.len()];
     let mut max_flow = I::zero();
-    let mut paths = BTreeMap::new();
+    let (source, sink) = match (nodes.get(&Node::Source), nodes.get(&Node::Sink)) {
+        (Some(source), Some(sink)) => (*source, *sink),
+        _ => {
+            return Ok(Solution {
+                nodes,
+                edges,
+                graph,
+                potential,
+                max_flow,
+                cancelled: false,
+                iterations: 0,
+            })
+        }
+    };
 
+    let mut cancelled = false;
+    let mut iterations = 0;
     loop {
-        let balance_source: I = graph
-            .edges_directed(Node::Source, Direction::Outgoing)
-            .map(|(_, _, EdgeWeight { capacity, .. })| *capacity)
-            .sum();
-        if balance_source == I::zero() {
-            // finish if there's no remaining flow
+        // find distance vector i.e. distance from source to every other node (w.r.t. reduced
+        // costs). This is where governance, AKA the 'priority-of-claims', comes into play through
+        // the edge costs.
+        let distance = shortest_paths(&graph, source, &potential);
+        let distance_s_t = match distance[sink] {
+            Some(d) => d,
+            None => break, // finish if there's no augmenting path left
+        };
+        if !progress.report(iterations, max_flow) {
+            cancelled = true;
             break;
         }
 
-        // find distance vector i.e. distance from source to every other node.
-        // This is a potential place for introducing governance, AKA the 'priority-of-claims'.
-        // trick: use a high cost for edges whose capacity is exhausted
-        let edge_weights = |(_, _, &EdgeWeight { cost, capacity })| {
-            if capacity > I::zero() {
-                cost
-            } else {
-                I::one()
-            }
-        };
-        let distance = dijkstra(&graph, Node::Source, Some(Node::Sink), edge_weights);
+        // update the potentials so that all edges on shortest paths have zero reduced cost
+        let mut near = vec![false; potential.len()];
+        for ((p, d), near) in potential.iter_mut().zip(distance).zip(near.iter_mut()) {
+            *near = matches!(d, Some(d) if d <= distance_s_t);
+            *p += min(d.unwrap_or(distance_s_t), distance_s_t);
+        }
 
-        let distance_s_t = distance[&Node::Sink];
+        // we define our admissable graph as the subgraph composed of residual edges that have a
+        // zero reduced cost and `capacity > 0`, restricted to nodes that are not farther away from
+        // the source than the sink
+        let admissable_graph = DiGraphMap::from_edges(
+            admissible_arcs(&graph, &potential, &near)
+                .fold(BTreeMap::<_, I>::new(), |mut acc, (u, arc)| {
+                    *acc.entry((u, graph.head[arc])).or_default() += graph.residual[arc];
+                    acc
+                })
+                .into_iter()
+                .map(|((u, v), capacity)| {
+                    (
+                        u,
+                        v,
+                        EdgeWeight {
+                            cost: I::zero(),
+                            capacity,
+                        },
+                    )
+                }),
+        );
 
-        // we define our admissable graph as a subgraph composed of edges that have a
-        // `cost <= distance-to-sink` (i.e. `distance_s_t`) and `capacity > 0`
-        let admissable_graph = EdgeFiltered::from_fn(&graph, |(_, _, e)| {
-            e.cost <= distance_s_t && e.capacity > I::zero()
+        let path = max_flow_algo.max_flow(&admissable_graph, source, sink)?;
+        let path_flow = path.iter().fold(I::zero(), |acc, ((u, v), flow)| {
+            match (*u == source, *v == source) {
+                (true, false) => acc + *flow,
+                (false, true) => acc - *flow,
+                _ => acc,
+            }
         });
-
-        let path = push_relabel_max_flow(&admissable_graph, Node::Source, Node::Sink).unwrap();
-        let path_flow = path
-            .iter()
-            .filter_map(|((debtor, _), cap)| (debtor == &Node::Source).then_some(*cap))
-            .sum();
         if path_flow == I::zero() {
             break;
         }
 
         max_flow += path_flow;
+        iterations += 1;
+        for ((u, v), flow) in path {
+            graph.push(u, v, flow, |graph, arc| {
+                graph.reduced_cost(u, arc, &potential) == I::zero()
+            });
+        }
+    }
+
+    // complete the flow to a max flow over all residual edges (regardless of their reduced costs),
+    // so that the result of a cancelled run is still feasible
+    if cancelled {
+        max_flow += graph.complete(source, sink, max_flow_algo)?;
+    }
 
-        path.into_iter().for_each(|(edge, flow)| {
-            let EdgeWeight { capacity, .. } = &mut graph[edge];
-            *capacity -= flow;
+    Ok(Solution {
+        nodes,
+        edges,
+        graph,
+        potential,
+        max_flow,
+        cancelled,
+        iterations,
+    })
+}
 
-            if let (Node::WithId(n1), Node::WithId(n2)) = edge {
-                *paths.entry((n1, n2)).or_default() += flow;
+impl<N, I> Solution<N, I>
+where
+    N: Id,
+    I: Int,
+{
+    fn paths(&self) -> FlowPaths<N, I> {
+        let ids: BTreeMap<_, _> = self.nodes.iter().map(|(node, idx)| (*idx, node)).collect();
+        self.edges
+            .iter()
+            .zip(self.graph.flows())
+            .filter_map(
+                |((u, v, _, _), flow)| match (ids[u].firm(), ids[v].firm()) {
+                    (Some(n1), Some(n2)) if flow > I::zero() => {
+                        Some(((n1.clone(), n2.clone()), flow))
+                    }
+                    _ => None,
+                },
+            )
+            .fold(BTreeMap::new(), |mut acc, (pair, flow)| {
+                *acc.entry(pair).or_default() += flow;
+                acc
+            })
+    }
+
+    /// The potentials keep the reduced cost of every residual arc non-negative (which is what
+    /// Dijkstra's algorithm relies on), so they prove the optimality of the flow.
+    fn certificate(&self, obligation_list: &FlowGraph<N, I, I>) -> Certificate<N, I> {
+        let flow = obligation_list
+            .keys()
+            .zip(self.graph.flows())
+            .filter(|(_, flow)| *flow > I::zero())
+            .map(|(edge, flow)| (edge.clone(), flow))
+            .collect();
+        let potentials = self
+            .nodes
+            .iter()
+            .map(|(node, idx)| (node.clone(), self.potential[*idx]))
+            .collect();
+        Certificate::with_potentials(obligation_list, flow, potentials)
+    }
+}
+
+/// Dijkstra's algorithm over the residual arcs w.r.t. the reduced costs.
+fn shortest_paths<I: Int>(graph: &Residual<I>, source: usize, potential: &[I]) -> Vec<Option<I>> {
+    let mut distance = vec![None; potential.len()];
+    let mut heap = BinaryHeap::new();
+    distance[source] = Some(I::zero());
+    heap.push(Reverse((I::zero(), source)));
+
+    while let Some(Reverse((d, u))) = heap.pop() {
+        if matches!(distance[u], Some(du) if d > du) {
+            continue;
+        }
+        for arc in graph.first[u]..graph.first[u + 1] {
+            if graph.residual[arc] <= I::zero() {
+                continue;
             }
-        });
+            let v = graph.head[arc];
+            let dv = d + graph.reduced_cost(u, arc, potential);
+            match distance[v] {
+                Some(old) if old <= dv => {}
+                _ => {
+                    distance[v] = Some(dv);
+                    heap.push(Reverse((dv, v)));
+                }
+            }
+        }
     }
 
-    (max_flow, paths)
+    distance
+}
+
+/// The residual arcs with a zero reduced cost between nodes that are `near` (together with their
+/// tails).
+fn admissible_arcs<'a, I: Int>(
+    graph: &'a Residual<I>,
+    potential: &'a [I],
+    near: &'a [bool],
+) -> impl Iterator<Item = (usize, usize)> + 'a {
+    graph.residual_arcs().filter(move |(u, arc)| {
+        near[*u] && near[graph.head[*arc]] && graph.reduced_cost(*u, *arc, potential) == I::zero()
+    })
 }
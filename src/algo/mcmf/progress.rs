@@ -0,0 +1,25 @@
+/// An observer of the progress of a min-cost flow algorithm, which can also cancel it (e.g. when a
+/// time budget runs out or a cancellation token is set).
+pub trait Progress<I> {
+    /// Called at every iteration with the flow pushed so far. Returns `false` to cancel the run.
+    fn report(&mut self, iteration: usize, flow: I) -> bool;
+}
+
+impl<I, F> Progress<I> for F
+where
+    F: FnMut(usize, I) -> bool,
+{
+    fn report(&mut self, iteration: usize, flow: I) -> bool {
+        self(iteration, flow)
+    }
+}
+
+/// Doesn't observe the progress and never cancels.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoProgress;
+
+impl<I> Progress<I> for NoProgress {
+    fn report(&mut self, _iteration: usize, _flow: I) -> bool {
+        true
+    }
+}
@@ -1,2 +1,3 @@
 pub mod max_flow;
 pub mod mcmf;
+mod residual;
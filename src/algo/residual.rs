@@ -0,0 +1,221 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+
+use num_traits::CheckedAdd;
+use petgraph::graphmap::DiGraphMap;
+
+use crate::algo::max_flow::{MaxFlow, MaxFlowError};
+use crate::algo::mcmf::EdgeWeight;
+use crate::int::Int;
+
+/// The residual network of a flow problem in compressed adjacency form. The outgoing arcs of node
+/// `u` are `first[u]..first[u + 1]` and every arc `a` is paired with its reversal `rev[a]`.
+pub(crate) struct Residual<I> {
+    pub(crate) first: Vec<usize>,
+    pub(crate) head: Vec<usize>,
+    pub(crate) rev: Vec<usize>,
+    pub(crate) residual: Vec<I>,
+    pub(crate) cost: Vec<I>,
+    /// The arc corresponding to each input edge
+    pub(crate) edge_arcs: Vec<usize>,
+}
+
+impl<I: Int> Residual<I> {
+    /// Builds the residual network of `n` nodes with the edges `(u, v, capacity, cost)`, where
+    /// negative capacities are treated the same as zero capacities.
+    pub(crate) fn new(n: usize, edges: &[(usize, usize, I, I)]) -> Self {
+        let mut first = vec![0; n + 1];
+        for (u, v, _, _) in edges {
+            first[*u + 1] += 1;
+            first[*v + 1] += 1;
+        }
+        for u in 0..n {
+            first[u + 1] += first[u];
+        }
+
+        let m = 2 * edges.len();
+        let mut head = vec![0; m];
+        let mut rev = vec![0; m];
+        let mut residual = vec![I::zero(); m];
+        let mut cost = vec![I::zero(); m];
+        let mut edge_arcs = Vec::with_capacity(edges.len());
+        let mut pos = first.clone();
+
+        for (u, v, capacity, c) in edges {
+            let (a, b) = (pos[*u], pos[*v]);
+            pos[*u] += 1;
+            pos[*v] += 1;
+
+            head[a] = *v;
+            rev[a] = b;
+            residual[a] = max(*capacity, I::zero());
+            cost[a] = *c;
+
+            head[b] = *u;
+            rev[b] = a;
+            cost[b] = -*c;
+
+            edge_arcs.push(a);
+        }
+
+        Self {
+            first,
+            head,
+            rev,
+            residual,
+            cost,
+            edge_arcs,
+        }
+    }
+
+    pub(crate) fn node_count(&self) -> usize {
+        self.first.len() - 1
+    }
+
+    pub(crate) fn tail(&self, arc: usize) -> usize {
+        self.head[self.rev[arc]]
+    }
+
+    pub(crate) fn augment(&mut self, arc: usize, amount: I) {
+        self.residual[arc] -= amount;
+        self.residual[self.rev[arc]] += amount;
+    }
+
+    /// The flow over every input edge (in input order).
+    pub(crate) fn flows(&self) -> impl Iterator<Item = I> + '_ {
+        self.edge_arcs
+            .iter()
+            .map(move |arc| self.residual[self.rev[*arc]])
+    }
+
+    pub(crate) fn reduced_cost(&self, u: usize, arc: usize, potential: &[I]) -> I {
+        self.cost[arc] + potential[u] - potential[self.head[arc]]
+    }
+
+    /// The arcs with a positive residual capacity (together with their tails).
+    pub(crate) fn residual_arcs(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.node_count()).flat_map(move |u| {
+            (self.first[u]..self.first[u + 1])
+                .filter(move |arc| self.residual[*arc] > I::zero())
+                .map(move |arc| (u, arc))
+        })
+    }
+
+    /// Push `flow` from `u` to `v` over the residual arcs between them that `admissible` accepts.
+    pub(crate) fn push(
+        &mut self,
+        u: usize,
+        v: usize,
+        mut flow: I,
+        admissible: impl Fn(&Self, usize) -> bool,
+    ) {
+        for arc in self.first[u]..self.first[u + 1] {
+            if flow == I::zero() {
+                break;
+            }
+            if self.head[arc] != v || self.residual[arc] <= I::zero() || !admissible(self, arc) {
+                continue;
+            }
+            let amount = min(flow, self.residual[arc]);
+            self.augment(arc, amount);
+            flow -= amount;
+        }
+        debug_assert!(flow == I::zero());
+    }
+
+    /// Completes the flow to a max flow from `source` to `sink` over all residual arcs (regardless
+    /// of their costs) using `max_flow_algo`, and returns the flow that was added.
+    pub(crate) fn complete<MF>(
+        &mut self,
+        source: usize,
+        sink: usize,
+        max_flow_algo: &mut MF,
+    ) -> Result<I, MaxFlowError>
+    where
+        MF: MaxFlow,
+        I: CheckedAdd,
+    {
+        let residual_graph = DiGraphMap::from_edges(
+            self.residual_arcs()
+                .fold(BTreeMap::<_, I>::new(), |mut acc, (u, arc)| {
+                    *acc.entry((u, self.head[arc])).or_default() += self.residual[arc];
+                    acc
+                })
+                .into_iter()
+                .map(|((u, v), capacity)| {
+                    (
+                        u,
+                        v,
+                        EdgeWeight {
+                            cost: I::zero(),
+                            capacity,
+                        },
+                    )
+                }),
+        );
+        if !residual_graph.contains_node(source) || !residual_graph.contains_node(sink) {
+            return Ok(I::zero());
+        }
+
+        let mut added = I::zero();
+        for ((u, v), flow) in max_flow_algo.max_flow(&residual_graph, source, sink)? {
+            match (u == source, v == source) {
+                (true, false) => added += flow,
+                (false, true) => added -= flow,
+                _ => {}
+            }
+            self.push(u, v, flow, |_, _| true);
+        }
+        Ok(added)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::max_flow::PushRelabel;
+
+    #[test]
+    fn arcs_are_paired_with_their_reversals() {
+        let graph = Residual::new(3, &[(0, 1, 5, 2), (1, 2, -3, 1), (0, 2, 4, 7)]);
+        assert_eq!(graph.node_count(), 3);
+        for (edge, (u, v, capacity, cost)) in [(0, 1, 5, 2), (1, 2, 0, 1), (0, 2, 4, 7)]
+            .into_iter()
+            .enumerate()
+        {
+            let arc = graph.edge_arcs[edge];
+            let rev = graph.rev[arc];
+            assert_eq!((graph.tail(arc), graph.head[arc]), (u, v));
+            assert_eq!((graph.tail(rev), graph.head[rev]), (v, u));
+            assert_eq!(graph.rev[rev], arc);
+            assert_eq!((graph.residual[arc], graph.residual[rev]), (capacity, 0));
+            assert_eq!((graph.cost[arc], graph.cost[rev]), (cost, -cost));
+        }
+    }
+
+    #[test]
+    fn complete_adds_to_the_flow_so_far() {
+        // 0 -> 1 -> 3 and 0 -> 2 -> 3, with one unit already routed over 0 -> 1 -> 2 -> 3
+        let edges = [
+            (0, 1, 2, 0),
+            (0, 2, 1, 0),
+            (1, 2, 1, 0),
+            (1, 3, 1, 0),
+            (2, 3, 2, 0),
+        ];
+        let mut graph = Residual::new(4, &edges);
+        for edge in [0, 2, 4] {
+            graph.augment(graph.edge_arcs[edge], 1);
+        }
+        assert_eq!(graph.flows().collect::<Vec<_>>(), [1, 0, 1, 0, 1]);
+
+        assert_eq!(graph.complete(0, 3, &mut PushRelabel), Ok(2));
+        let flows: Vec<_> = graph.flows().collect();
+        assert_eq!(flows[3] + flows[4], 3);
+        for ((_, _, capacity, _), flow) in edges.iter().zip(flows) {
+            assert!(0 <= flow && flow <= *capacity);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{min, Reverse};
+
+use num_traits::{CheckedMul, One, Zero};
+
+use crate::error::Error;
+use crate::int::Int;
+use crate::obligation::Obligation;
+
+/// A policy that decides how the amount cleared between a pair of firms is split among the
+/// obligations they share.
+pub trait AllocationPolicy<O: Obligation> {
+    /// Splits `cleared` among `obligations`, which all have the same debtor and creditor and are
+    /// given in input order. Returns the set-off amount of every obligation (in the same order).
+    ///
+    /// `cleared` is always less than the sum of the obligation amounts. The returned amounts must
+    /// sum up to `cleared` and must not exceed the amount of their obligation.
+    fn allocate(&self, cleared: O::Amount, obligations: &[&O]) -> Result<Vec<O::Amount>, Error>;
+}
+
+impl<O, F> AllocationPolicy<O> for F
+where
+    O: Obligation,
+    F: Fn(O::Amount, &[&O]) -> Vec<O::Amount>,
+{
+    fn allocate(&self, cleared: O::Amount, obligations: &[&O]) -> Result<Vec<O::Amount>, Error> {
+        Ok(self(cleared, obligations))
+    }
+}
+
+/// Fully sets off the obligations in input order, i.e. the first obligations are fully set off and
+/// the later ones get nothing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct InputOrder;
+
+impl<O> AllocationPolicy<O> for InputOrder
+where
+    O: Obligation,
+    O::Amount: Int,
+{
+    fn allocate(&self, cleared: O::Amount, obligations: &[&O]) -> Result<Vec<O::Amount>, Error> {
+        Ok(greedy(cleared, obligations, 0..obligations.len()))
+    }
+}
+
+/// Sets off every obligation in proportion to its amount. The units that remain after rounding
+/// down go to the obligations with the largest rounding error.
+///
+/// Fails if the product of the cleared amount and an obligation amount overflows.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProRata;
+
+impl<O> AllocationPolicy<O> for ProRata
+where
+    O: Obligation,
+    O::Amount: Int + CheckedMul,
+{
+    fn allocate(&self, cleared: O::Amount, obligations: &[&O]) -> Result<Vec<O::Amount>, Error> {
+        let total: O::Amount = obligations.iter().map(|o| o.amount()).sum();
+
+        // the exact share of every obligation, i.e. the quotient and the remainder of its product
+        // with the cleared amount divided by the total
+        let exact = obligations
+            .iter()
+            .map(|o| {
+                let product = cleared
+                    .checked_mul(&o.amount())
+                    .ok_or(Error::AllocationOverflow)?;
+                let share = product / total;
+                Ok((share, product - share * total))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut shares: Vec<_> = exact.iter().map(|(share, _)| *share).collect();
+        let mut leftover = cleared - shares.iter().copied().sum::<O::Amount>();
+
+        let mut order: Vec<_> = (0..obligations.len()).collect();
+        order.sort_by_key(|i| Reverse(exact[*i].1));
+        for i in order {
+            if leftover <= O::Amount::zero() {
+                break;
+            }
+            shares[i] += O::Amount::one();
+            leftover -= O::Amount::one();
+        }
+
+        Ok(shares)
+    }
+}
+
+/// Fully sets off the oldest obligations first, assuming that obligation ids are assigned in
+/// chronological order. Obligations without an id come last.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OldestFirst;
+
+impl<O> AllocationPolicy<O> for OldestFirst
+where
+    O: Obligation,
+    O::Amount: Int,
+{
+    fn allocate(&self, cleared: O::Amount, obligations: &[&O]) -> Result<Vec<O::Amount>, Error> {
+        let mut order: Vec<_> = (0..obligations.len()).collect();
+        order.sort_by_key(|i| (obligations[*i].id().is_none(), obligations[*i].id()));
+        Ok(greedy(cleared, obligations, order))
+    }
+}
+
+/// Fully sets off the largest obligations first.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LargestFirst;
+
+impl<O> AllocationPolicy<O> for LargestFirst
+where
+    O: Obligation,
+    O::Amount: Int,
+{
+    fn allocate(&self, cleared: O::Amount, obligations: &[&O]) -> Result<Vec<O::Amount>, Error> {
+        let mut order: Vec<_> = (0..obligations.len()).collect();
+        order.sort_by_key(|i| Reverse(obligations[*i].amount()));
+        Ok(greedy(cleared, obligations, order))
+    }
+}
+
+/// Fully sets off the smallest obligations first, which maximises the number of obligations that
+/// are discharged completely.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SmallestFirst;
+
+impl<O> AllocationPolicy<O> for SmallestFirst
+where
+    O: Obligation,
+    O::Amount: Int,
+{
+    fn allocate(&self, cleared: O::Amount, obligations: &[&O]) -> Result<Vec<O::Amount>, Error> {
+        let mut order: Vec<_> = (0..obligations.len()).collect();
+        order.sort_by_key(|i| obligations[*i].amount());
+        Ok(greedy(cleared, obligations, order))
+    }
+}
+
+// Fully set off the obligations in the specified order until `cleared` is used up
+fn greedy<O>(
+    mut cleared: O::Amount,
+    obligations: &[&O],
+    order: impl IntoIterator<Item = usize>,
+) -> Vec<O::Amount>
+where
+    O: Obligation,
+    O::Amount: Int,
+{
+    let mut set_offs = vec![O::Amount::zero(); obligations.len()];
+    for i in order {
+        let set_off = min(cleared, obligations[i].amount());
+        set_offs[i] = set_off;
+        cleared -= set_off;
+    }
+    set_offs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obligation::SimpleObligation;
+
+    fn obligations(amounts: &[i64]) -> Vec<SimpleObligation<u64, i64>> {
+        amounts
+            .iter()
+            .map(|amount| SimpleObligation::new(None, 1, 2, *amount).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn greedy_policies_fully_set_off_in_their_order() {
+        // the second obligation has no id, so it is the youngest
+        let on: Vec<_> = [(Some(0), 3), (None, 5), (Some(1), 2)]
+            .into_iter()
+            .map(|(id, amount)| SimpleObligation::new(id, 1, 2, amount).unwrap())
+            .collect();
+        let on: Vec<_> = on.iter().collect();
+        assert_eq!(InputOrder.allocate(4, &on).unwrap(), [3, 1, 0]);
+        assert_eq!(OldestFirst.allocate(4, &on).unwrap(), [3, 0, 1]);
+        assert_eq!(LargestFirst.allocate(4, &on).unwrap(), [0, 4, 0]);
+        assert_eq!(SmallestFirst.allocate(4, &on).unwrap(), [2, 0, 2]);
+    }
+
+    #[test]
+    fn pro_rata_rounds_to_the_largest_remainders() {
+        let on = obligations(&[1, 2, 3]);
+        let on: Vec<_> = on.iter().collect();
+        assert_eq!(ProRata.allocate(4, &on).unwrap(), [1, 1, 2]);
+    }
+
+    #[test]
+    fn pro_rata_overflow_is_an_error() {
+        let on = obligations(&[4_000_000_000, 4_000_000_000]);
+        let on: Vec<_> = on.iter().collect();
+        assert!(matches!(
+            ProRata.allocate(4_000_000_000, &on),
+            Err(Error::AllocationOverflow)
+        ));
+    }
+}
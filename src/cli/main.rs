@@ -1,38 +1,223 @@
 #![warn(clippy::all, clippy::pedantic)]
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::fs::File;
 use std::io::{Read, Write};
+use std::ops::Add;
 use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use csv::{Reader as CsvReader, Writer as CsvWriter};
 use log::LevelFilter;
 use mtcs::{
-    algo::mcmf::primal_dual::PrimalDual, obligation::SimpleObligation, setoff::SimpleSetoff,
+    algo::mcmf::primal_dual::PrimalDual,
+    allocation::{AllocationPolicy, InputOrder, LargestFirst, OldestFirst, ProRata, SmallestFirst},
+    cycle::{decompose_cycles_with_bilateral, Cycle, CycleKind},
+    obligation::{Obligation, SimpleObligation},
+    payment::{settlement_plan, Clearing},
+    setoff::SimpleSetoff,
+    verify::{verify, Violation},
     ComplexIdMtcs, DefaultMtcs, Mtcs,
 };
 use num_traits::Zero;
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use simplelog::{Config as SimpleLoggerConfig, SimpleLogger};
 
 /// Tool for running Multilateral Trade Credit Set-off (MTCS) on an obligation network
 #[derive(Parser, Debug)]
-#[command(version, long_about = None)]
+#[command(version, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
-    /// Path to input CSV file with obligations (fields - `id` (optional), `debtor`, `creditor`, `amount`)
-    #[arg(short, long)]
-    input_file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Path to input CSV file with obligations (fields - `id` (optional), `debtor`, `creditor`, `amount`, `priority` (optional), `indivisible` (optional))
+    #[arg(short, long, required = true)]
+    input_file: Option<PathBuf>,
 
     /// Path to output CSV file
+    #[arg(short, long, required = true)]
+    output_file: Option<PathBuf>,
+
+    /// Path to output CSV file with the cleared cycles (fields - `cycle`, `kind`, `id`, `debtor`, `creditor`, `amount`)
+    #[arg(short, long)]
+    cycles_file: Option<PathBuf>,
+
+    /// Path to input CSV file with the cash available to firms (fields - `firm`, `cash`)
+    #[arg(
+        short,
+        long,
+        requires = "payments_file",
+        conflicts_with = "cycles_file"
+    )]
+    liquidity_file: Option<PathBuf>,
+
+    /// Path to output CSV file with the cash payments (fields - `payer`, `payee`, `amount`)
+    #[arg(short, long, requires = "liquidity_file")]
+    payments_file: Option<PathBuf>,
+
+    /// Path to output CSV file with the payments that settle the remainders (fields - `payer`, `payee`, `amount`)
+    #[arg(short, long)]
+    settlement_file: Option<PathBuf>,
+
+    /// Net the debts of firms that owe each other bilaterally before clearing the rest multilaterally
+    #[arg(short, long)]
+    bilateral: bool,
+
+    /// Path to input CSV file with the obligations that aren't eligible for set-off (fields - `id`, `reason`)
+    #[arg(short = 'e', long)]
+    ineligible_file: Option<PathBuf>,
+
+    /// Path to input CSV file with the set-off caps and opt-outs of firms (fields - `firm`, `cap` (optional), `excluded` (optional))
+    #[arg(short, long)]
+    firm_limits_file: Option<PathBuf>,
+
+    /// Path to output JSON file with the run statistics
     #[arg(short, long)]
-    output_file: PathBuf,
+    report_file: Option<PathBuf>,
+
+    /// Only set off along cycles of at most this many firms
+    #[arg(short, long, conflicts_with = "liquidity_file")]
+    max_cycle_length: Option<usize>,
+
+    /// Stop the min-cost flow after this many seconds and return the (feasible but possibly not optimal) set-off found so far
+    #[arg(short, long, value_name = "SECONDS", value_parser = parse_seconds)]
+    time_limit: Option<Duration>,
+
+    /// Policy for splitting the amount cleared between two firms among their obligations
+    #[arg(short, long, value_enum, default_value = "input-order")]
+    allocation: Allocation,
 
     /// Log level
-    #[arg(short, long, action = clap::ArgAction::Count)]
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
     verbose: u8,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Verify set-offs against the obligations they were computed from
+    Verify(VerifyArgs),
+}
+
+#[derive(clap::Args, Debug)]
+#[allow(clippy::struct_field_names)] // the flags match those of the clearing
+struct VerifyArgs {
+    /// Path to input CSV file with obligations (fields - `id` (optional), `debtor`, `creditor`, `amount`)
+    #[arg(short, long)]
+    input_file: PathBuf,
+
+    /// Path to input CSV file with the set-offs to verify (fields - `id` (optional), `debtor`, `creditor`, `amount`, `set_off`, `remainder`)
+    #[arg(short, long)]
+    setoffs_file: PathBuf,
+
+    /// Path to output CSV file with the violations found (fields - `violation`, `id`, `debtor`, `creditor`, `firm`, `expected`, `actual`)
+    #[arg(short, long)]
+    report_file: PathBuf,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Allocation {
+    InputOrder,
+    ProRata,
+    OldestFirst,
+    LargestFirst,
+    SmallestFirst,
+}
+
+// The cash available to a firm
+#[derive(Deserialize)]
+struct LiquidityRow<AccountId, Amount> {
+    firm: AccountId,
+    cash: Amount,
+}
+
+// Read the cash available to firms from CSV file
+fn read_liquidity_csv<AccountId, Amount>(reader: impl Read) -> BTreeMap<AccountId, Amount>
+where
+    AccountId: Ord + DeserializeOwned,
+    Amount: DeserializeOwned,
+{
+    let mut rdr = CsvReader::from_reader(reader);
+    let rows: Result<Vec<LiquidityRow<AccountId, Amount>>, _> = rdr.deserialize().collect();
+    rows.unwrap()
+        .into_iter()
+        .map(|row| (row.firm, row.cash))
+        .collect()
+}
+
+// An obligation that isn't eligible for set-off and why
+#[derive(Deserialize)]
+struct IneligibleRow {
+    id: usize,
+    reason: String,
+}
+
+// Read the reasons why obligations aren't eligible for set-off from CSV file (by obligation id)
+fn read_ineligible_csv(reader: impl Read) -> BTreeMap<usize, String> {
+    let mut rdr = CsvReader::from_reader(reader);
+    let rows: Result<Vec<IneligibleRow>, _> = rdr.deserialize().collect();
+    rows.unwrap()
+        .into_iter()
+        .map(|row| (row.id, row.reason))
+        .collect()
+}
+
+// The set-off cap of a firm and whether it opted out of the set-off
+#[derive(Deserialize)]
+struct FirmLimitsRow<AccountId, Amount> {
+    firm: AccountId,
+    cap: Option<Amount>,
+    excluded: Option<bool>,
+}
+
+// The set-off caps and opt-outs of all firms
+struct FirmLimits<AccountId, Amount> {
+    caps: BTreeMap<AccountId, Amount>,
+    excluded: BTreeSet<AccountId>,
+}
+
+impl<AccountId, Amount> Default for FirmLimits<AccountId, Amount> {
+    fn default() -> Self {
+        Self {
+            caps: BTreeMap::new(),
+            excluded: BTreeSet::new(),
+        }
+    }
+}
+
+// The options of a clearing run
+struct RunOptions {
+    liquidity: Option<BTreeMap<i32, i64>>,
+    bilateral: bool,
+    max_cycle_length: Option<usize>,
+    limits: FirmLimits<i32, i64>,
+    ineligible: BTreeMap<usize, String>,
+    time_limit: Option<Duration>,
+}
+
+// Read the set-off caps and opt-outs of firms from CSV file
+fn read_firm_limits_csv<AccountId, Amount>(reader: impl Read) -> FirmLimits<AccountId, Amount>
+where
+    AccountId: Ord + Clone + DeserializeOwned,
+    Amount: DeserializeOwned,
+{
+    let mut rdr = CsvReader::from_reader(reader);
+    let rows: Result<Vec<FirmLimitsRow<AccountId, Amount>>, _> = rdr.deserialize().collect();
+    rows.unwrap()
+        .into_iter()
+        .fold(FirmLimits::default(), |mut acc, row| {
+            if row.excluded.unwrap_or_default() {
+                acc.excluded.insert(row.firm.clone());
+            }
+            if let Some(cap) = row.cap {
+                acc.caps.insert(row.firm, cap);
+            }
+            acc
+        })
+}
+
 // Read the obligations from CSV file
 fn read_obligations_csv<AccountId, Amount>(
     reader: impl Read,
@@ -46,9 +231,41 @@ where
     rows.unwrap()
 }
 
-// Write the clearing results to CSV file
-fn write_csv<AccountId, Amount>(
-    res: &[SimpleSetoff<AccountId, Amount>],
+// Read the set-offs from CSV file
+fn read_setoffs_csv<AccountId, Amount>(reader: impl Read) -> Vec<SimpleSetoff<AccountId, Amount>>
+where
+    AccountId: DeserializeOwned,
+    Amount: DeserializeOwned,
+{
+    let mut rdr = CsvReader::from_reader(reader);
+    let rows: Result<Vec<_>, _> = rdr.deserialize().collect();
+    rows.unwrap()
+}
+
+// Write the clearing results (i.e. set-offs or payments) to CSV file
+fn write_csv<T: Serialize>(res: &[T], writer: impl Write) -> Result<(), Box<dyn Error>> {
+    let mut wtr = CsvWriter::from_writer(writer);
+    for row in res {
+        wtr.serialize(row)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// A member obligation of a cleared cycle
+#[derive(Serialize)]
+struct CycleRow<'a, AccountId, Amount> {
+    cycle: usize,
+    kind: CycleKind,
+    id: Option<usize>,
+    debtor: &'a AccountId,
+    creditor: &'a AccountId,
+    amount: &'a Amount,
+}
+
+// Write the cleared cycles to CSV file, one row per member obligation
+fn write_cycles_csv<AccountId, Amount>(
+    cycles: &[Cycle<AccountId, Amount>],
     writer: impl Write,
 ) -> Result<(), Box<dyn Error>>
 where
@@ -56,13 +273,135 @@ where
     Amount: Serialize,
 {
     let mut wtr = CsvWriter::from_writer(writer);
-    for setoff in res {
-        wtr.serialize(setoff)?;
+    for (cycle_idx, cycle) in cycles.iter().enumerate() {
+        let creditors = cycle.firms.iter().cycle().skip(1);
+        for ((id, debtor), creditor) in cycle.obligations.iter().zip(&cycle.firms).zip(creditors) {
+            wtr.serialize(CycleRow {
+                cycle: cycle_idx,
+                kind: cycle.kind,
+                id: *id,
+                debtor,
+                creditor,
+                amount: &cycle.amount,
+            })?;
+        }
     }
     wtr.flush()?;
     Ok(())
 }
 
+// A violation found by the verification, with the amount that was expected and the actual one
+#[derive(Serialize)]
+struct ViolationRow<AccountId, Amount> {
+    violation: &'static str,
+    id: Option<usize>,
+    debtor: Option<AccountId>,
+    creditor: Option<AccountId>,
+    firm: Option<AccountId>,
+    expected: Option<Amount>,
+    actual: Option<Amount>,
+}
+
+impl<AccountId, Amount> From<Violation<AccountId, Amount>> for ViolationRow<AccountId, Amount>
+where
+    Amount: Add<Output = Amount>,
+{
+    fn from(violation: Violation<AccountId, Amount>) -> Self {
+        let row = |violation, id, debtor, creditor, expected, actual| ViolationRow {
+            violation,
+            id,
+            debtor: Some(debtor),
+            creditor: Some(creditor),
+            firm: None,
+            expected,
+            actual,
+        };
+        match violation {
+            Violation::MissingSetOff {
+                id,
+                debtor,
+                creditor,
+                amount,
+            } => row("missing-set-off", id, debtor, creditor, Some(amount), None),
+            Violation::UnknownSetOff {
+                id,
+                debtor,
+                creditor,
+                amount,
+            } => row("unknown-set-off", id, debtor, creditor, None, Some(amount)),
+            Violation::InconsistentAmounts {
+                id,
+                debtor,
+                creditor,
+                amount,
+                set_off,
+                remainder,
+            } => row(
+                "inconsistent-amounts",
+                id,
+                debtor,
+                creditor,
+                Some(amount),
+                Some(set_off + remainder),
+            ),
+            Violation::SetOffOutOfRange {
+                id,
+                debtor,
+                creditor,
+                amount,
+                set_off,
+            } => row(
+                "set-off-out-of-range",
+                id,
+                debtor,
+                creditor,
+                Some(amount),
+                Some(set_off),
+            ),
+            Violation::UnbalancedFirm {
+                firm,
+                as_debtor,
+                as_creditor,
+            } => ViolationRow {
+                violation: "unbalanced-firm",
+                id: None,
+                debtor: None,
+                creditor: None,
+                firm: Some(firm),
+                expected: Some(as_debtor),
+                actual: Some(as_creditor),
+            },
+        }
+    }
+}
+
+// Verify the set-offs against the obligations and write the violations found to the report
+fn run_verify(args: VerifyArgs) -> Result<(), Box<dyn Error>> {
+    let on: Vec<SimpleObligation<i32, i64>> = read_obligations_csv(&File::open(args.input_file)?);
+    let setoffs: Vec<SimpleSetoff<i32, i64>> = read_setoffs_csv(&File::open(args.setoffs_file)?);
+
+    let report = verify(&on, &setoffs).map_err(|e| format!("Verification failed: {e}"))?;
+    log::info!("Total debt = {}", report.total_debt);
+    log::info!("Total set-off = {}", report.total_set_off);
+    log::info!("Total remainder = {}", report.total_remainder);
+    log::info!("Violations = {}", report.violations.len());
+
+    let valid = report.is_valid();
+    let rows: Vec<ViolationRow<_, _>> = report.violations.into_iter().map(Into::into).collect();
+    write_csv(&rows, &File::create(args.report_file)?)?;
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Verification found {} violations", rows.len()).into())
+    }
+}
+
+// Parse a duration in (fractional) seconds, which must be non-negative and finite
+fn parse_seconds(arg: &str) -> Result<Duration, String> {
+    let secs: f64 = arg.parse().map_err(|e| format!("{e}"))?;
+    Duration::try_from_secs_f64(secs).map_err(|e| format!("{e}"))
+}
+
 fn log_level_from_u8(level: u8) -> LevelFilter {
     match level {
         0 => LevelFilter::Off,
@@ -72,6 +411,99 @@ fn log_level_from_u8(level: u8) -> LevelFilter {
     }
 }
 
+// The (monotonic) time elapsed since the first call, for measuring the phases of a run
+fn clock() -> Duration {
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed()
+}
+
+// Run the MTCS algorithm and check the result
+fn run_mtcs<Policy>(
+    on: Vec<SimpleObligation<i32, i64>>,
+    options: &RunOptions,
+    policy: Policy,
+) -> Clearing<SimpleSetoff<i32, i64>, i32, i64>
+where
+    Policy: AllocationPolicy<SimpleObligation<usize, i64>>
+        + AllocationPolicy<SimpleObligation<i32, i64>>,
+{
+    let now = Instant::now();
+
+    let RunOptions {
+        liquidity,
+        bilateral,
+        max_cycle_length,
+        limits,
+        ineligible,
+        time_limit,
+    } = options;
+    let limited = !limits.caps.is_empty() || !limits.excluded.is_empty();
+    if liquidity.is_some()
+        || *bilateral
+        || max_cycle_length.is_some()
+        || limited
+        || !ineligible.is_empty()
+        || time_limit.is_some()
+    {
+        // the solver is cancelled once the deadline (if any) has passed
+        let deadline = time_limit.map(|limit| now + limit);
+        let algo = PrimalDual::default().with_progress(
+            move |_, _| !matches!(deadline, Some(deadline) if Instant::now() >= deadline),
+        );
+        let mut mtcs = DefaultMtcs::with_allocation_policy(algo, policy)
+            .with_eligibility(|o: &SimpleObligation<i32, i64>| {
+                o.id().and_then(|id| ineligible.get(&id).cloned())
+            })
+            .with_clock(clock);
+        if *bilateral {
+            mtcs = mtcs.with_bilateral_netting();
+        }
+        if let Some(max_len) = *max_cycle_length {
+            mtcs = mtcs.with_max_cycle_length(max_len);
+        }
+        if limited {
+            mtcs = mtcs
+                .with_caps(limits.caps.clone())
+                .with_excluded(limits.excluded.clone());
+        }
+        let res = mtcs
+            .run_with_liquidity(on, liquidity.as_ref().unwrap_or(&BTreeMap::new()))
+            .expect("MTCS run failed");
+        let elapsed = now.elapsed();
+        log::info!("Run time: {elapsed:?}");
+        if !res.optimal {
+            log::warn!("Time limit exceeded, the set-off might not be optimal");
+        }
+
+        mtcs.check_with_payments(&res.setoffs, &res.payments)
+            .expect("MTCS check failed");
+        return res;
+    }
+
+    let mut mtcs = ComplexIdMtcs::wrapping(
+        DefaultMtcs::with_allocation_policy(PrimalDual::default(), policy).with_clock(clock),
+    );
+    let res = mtcs.run(on).expect("MTCS run failed");
+    let elapsed = now.elapsed();
+    log::info!("Run time: {elapsed:?}");
+
+    mtcs.check(&res).expect("MTCS check failed");
+    let report = mtcs
+        .inner()
+        .last_report()
+        .cloned()
+        .expect("report of the run");
+    Clearing {
+        bilateral: vec![0; res.len()],
+        setoffs: res,
+        payments: vec![],
+        cycles: vec![],
+        lost: report.lost,
+        optimal: report.optimal,
+        report,
+    }
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     // Parse CLI args
     let args = Args::parse();
@@ -80,21 +512,116 @@ fn main() -> Result<(), Box<dyn Error>> {
     let log_level = log_level_from_u8(args.verbose);
     SimpleLogger::init(log_level, SimpleLoggerConfig::default()).unwrap();
 
+    if let Some(Command::Verify(verify_args)) = args.command {
+        return run_verify(verify_args);
+    }
+    let input_file = args.input_file.expect("required argument");
+    let output_file = args.output_file.expect("required argument");
+
     // Read the obligations from the input CSV file
-    let input_file = File::open(args.input_file)?;
+    let input_file = File::open(input_file)?;
     let on: Vec<SimpleObligation<i32, i64>> = read_obligations_csv(&input_file);
 
+    // Read the cash available to firms from the liquidity CSV file (if any)
+    let liquidity = match args.liquidity_file {
+        Some(liquidity_file) => Some(read_liquidity_csv(&File::open(liquidity_file)?)),
+        None => None,
+    };
+
+    // Read the set-off caps and opt-outs of firms from the firm limits CSV file (if any)
+    let limits = match args.firm_limits_file {
+        Some(firm_limits_file) => read_firm_limits_csv(&File::open(firm_limits_file)?),
+        None => FirmLimits::default(),
+    };
+
+    // Read the obligations that aren't eligible for set-off from the ineligible CSV file (if any)
+    let ineligible = match args.ineligible_file {
+        Some(ineligible_file) => read_ineligible_csv(&File::open(ineligible_file)?),
+        None => BTreeMap::new(),
+    };
+
     // Run the MTCS algorithm
-    let now = std::time::Instant::now();
+    let max_len = args.max_cycle_length;
+    let options = RunOptions {
+        liquidity,
+        bilateral: args.bilateral,
+        max_cycle_length: max_len,
+        limits,
+        ineligible,
+        time_limit: args.time_limit,
+    };
+    let Clearing {
+        setoffs: res,
+        payments,
+        bilateral,
+        cycles,
+        report,
+        ..
+    } = match args.allocation {
+        Allocation::InputOrder => run_mtcs(on, &options, InputOrder),
+        Allocation::ProRata => run_mtcs(on, &options, ProRata),
+        Allocation::OldestFirst => run_mtcs(on, &options, OldestFirst),
+        Allocation::LargestFirst => run_mtcs(on, &options, LargestFirst),
+        Allocation::SmallestFirst => run_mtcs(on, &options, SmallestFirst),
+    };
 
-    let mut mtcs = ComplexIdMtcs::wrapping(DefaultMtcs::new(PrimalDual::default()));
-    let res = mtcs.run(on).expect("MTCS run failed");
-    let elapsed = now.elapsed();
-    log::info!("Run time: {elapsed:?}");
+    // Write the run statistics to the report JSON file (if requested)
+    if let Some(report_file) = args.report_file {
+        let report_file = File::create(report_file)?;
+        serde_json::to_writer_pretty(report_file, &report)?;
+    }
 
-    mtcs.check(&res).expect("MTCS check failed");
+    // Write the cash payments to the payments CSV file (if requested)
+    if let Some(payments_file) = args.payments_file {
+        let payments_file = File::create(payments_file)?;
+        write_csv(&payments, &payments_file)?;
+    }
+
+    // Write the payments that settle the remainders to the settlement CSV file (if requested)
+    if let Some(settlement_file) = args.settlement_file {
+        let plan = settlement_plan(&res);
+        let settled: i64 = plan.iter().map(|p| p.amount).sum();
+        log::info!("Total settled = {settled}");
+        let settlement_file = File::create(settlement_file)?;
+        write_csv(&plan, &settlement_file)?;
+    }
+
+    // Write the cleared cycles to the cycles CSV file (if requested)
+    if let Some(cycles_file) = args.cycles_file {
+        // with a max cycle length, the set-offs might not decompose into short cycles again
+        let cycles = match max_len {
+            Some(_) => cycles,
+            None => decompose_cycles_with_bilateral(&res, &bilateral)
+                .expect("Cycle decomposition failed"),
+        };
+        let cycles_file = File::create(cycles_file)?;
+        write_cycles_csv(&cycles, &cycles_file)?;
+    }
 
     // Write the result to the output CSV file
-    let output_file = File::create(args.output_file)?;
+    let output_file = File::create(output_file)?;
     write_csv(&res, &output_file)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_time_limit(limit: &str) -> Result<Option<Duration>, clap::Error> {
+        let time_limit = format!("--time-limit={limit}");
+        Args::try_parse_from(["mtcs-cli", "-i", "in.csv", "-o", "out.csv", &time_limit])
+            .map(|args| args.time_limit)
+    }
+
+    #[test]
+    fn time_limits_are_validated() {
+        assert_eq!(
+            parse_time_limit("1.5").unwrap(),
+            Some(Duration::from_millis(1500))
+        );
+        for limit in ["-1", "NaN", "inf", "1e300", "soon"] {
+            let error = parse_time_limit(limit).unwrap_err();
+            assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+        }
+    }
+}
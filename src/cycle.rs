@@ -0,0 +1,394 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::min;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::id::Id;
+use crate::int::Int;
+use crate::setoff::SetOff;
+
+/// A simple cycle of obligations that was (partially) cleared by a set-off.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Cycle<AccountId, Amount> {
+    /// The ids of the member obligations in cycle order, i.e. the creditor of every obligation is
+    /// the debtor of the next one
+    pub obligations: Vec<Option<usize>>,
+    /// The debtors of the member obligations
+    pub firms: Vec<AccountId>,
+    /// The amount that was set off along the cycle
+    pub amount: Amount,
+    /// Whether the cycle was netted bilaterally or cleared multilaterally
+    pub kind: CycleKind,
+}
+
+/// The way a cycle was cleared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CycleKind {
+    /// Netted by the bilateral pre-pass, i.e. a pair of reciprocal obligations
+    Bilateral,
+    /// Cleared by the min-cost flow
+    Multilateral,
+}
+
+/// Decomposes the cleared flow of a set-off (i.e. the `set_off` amounts of the set-off notices)
+/// into simple cycles.
+///
+/// Every cleared unit of debt is part of exactly one of the returned cycles, i.e. for every
+/// obligation the amounts of the cycles it is a member of sum up to its `set_off` amount.
+/// Returns an error if the `set_off` amounts are not balanced for every firm.
+pub fn decompose_cycles<SO, AccountId, Amount>(
+    setoffs: &[SO],
+) -> Result<Vec<Cycle<AccountId, Amount>>, Error>
+where
+    SO: SetOff<AccountId = AccountId, Amount = Amount>,
+    AccountId: Id,
+    Amount: Int,
+{
+    decompose_cycles_with_bilateral(setoffs, &vec![Amount::zero(); setoffs.len()])
+}
+
+/// Same as `decompose_cycles`, but the `bilateral` part of the `set_off` of every set-off notice
+/// (in the same order, see `Clearing::bilateral`) is decomposed into `CycleKind::Bilateral` cycles
+/// of two obligations. Only the rest is decomposed into `CycleKind::Multilateral` cycles.
+pub fn decompose_cycles_with_bilateral<SO, AccountId, Amount>(
+    setoffs: &[SO],
+    bilateral: &[Amount],
+) -> Result<Vec<Cycle<AccountId, Amount>>, Error>
+where
+    SO: SetOff<AccountId = AccountId, Amount = Amount>,
+    AccountId: Id,
+    Amount: Int,
+{
+    if bilateral.len() != setoffs.len()
+        || setoffs
+            .iter()
+            .zip(bilateral)
+            .any(|(so, b)| *b < Amount::zero() || *b > so.set_off())
+    {
+        return Err(Error::UnbalancedSetOff);
+    }
+
+    // match the bilateral set-offs of every pair of firms with those in the opposite direction
+    let pairs = setoffs
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| bilateral[*i] > Amount::zero())
+        .fold(BTreeMap::<_, Vec<_>>::new(), |mut acc, (i, so)| {
+            acc.entry((so.debtor(), so.creditor())).or_default().push(i);
+            acc
+        });
+    let mut cycles = vec![];
+    for ((debtor, creditor), forward) in &pairs {
+        if debtor > creditor {
+            continue;
+        }
+        let backward = pairs
+            .get(&(*creditor, *debtor))
+            .ok_or(Error::UnbalancedSetOff)?;
+        let mut remaining_forward: Vec<_> = forward.iter().map(|i| bilateral[*i]).collect();
+        let mut remaining_backward: Vec<_> = backward.iter().map(|i| bilateral[*i]).collect();
+        let (mut f, mut b) = (0, 0);
+        while f < forward.len() && b < backward.len() {
+            let amount = min(remaining_forward[f], remaining_backward[b]);
+            cycles.push(Cycle {
+                obligations: vec![setoffs[forward[f]].id(), setoffs[backward[b]].id()],
+                firms: vec![(*debtor).clone(), (*creditor).clone()],
+                amount,
+                kind: CycleKind::Bilateral,
+            });
+            remaining_forward[f] -= amount;
+            remaining_backward[b] -= amount;
+            if remaining_forward[f] == Amount::zero() {
+                f += 1;
+            }
+            if remaining_backward[b] == Amount::zero() {
+                b += 1;
+            }
+        }
+        if f < forward.len() || b < backward.len() {
+            return Err(Error::UnbalancedSetOff);
+        }
+    }
+    if pairs
+        .keys()
+        .any(|(debtor, creditor)| debtor > creditor && !pairs.contains_key(&(*creditor, *debtor)))
+    {
+        return Err(Error::UnbalancedSetOff);
+    }
+
+    let mut remaining: Vec<_> = setoffs
+        .iter()
+        .zip(bilateral)
+        .map(|(so, b)| so.set_off() - *b)
+        .collect();
+    let outgoing = setoffs
+        .iter()
+        .enumerate()
+        .filter(|(_, so)| so.set_off() > Amount::zero())
+        .fold(BTreeMap::<_, Vec<_>>::new(), |mut acc, (i, so)| {
+            acc.entry(so.debtor()).or_default().push(i);
+            acc
+        });
+    // the first outgoing set-off of every firm that might have some flow left
+    let mut next: BTreeMap<_, usize> = outgoing.keys().map(|firm| (*firm, 0)).collect();
+
+    for start in 0..setoffs.len() {
+        while remaining[start] > Amount::zero() {
+            // follow the cleared flow until we hit a firm we have already visited
+            let mut path = vec![start];
+            let mut visited = BTreeMap::new();
+            visited.insert(setoffs[start].debtor(), 0);
+            let mut firm = setoffs[start].creditor();
+            let begin = loop {
+                if let Some(pos) = visited.get(firm) {
+                    break *pos;
+                }
+                visited.insert(firm, path.len());
+
+                let edges = outgoing.get(firm).ok_or(Error::UnbalancedSetOff)?;
+                let pos = next.get_mut(firm).ok_or(Error::UnbalancedSetOff)?;
+                while *pos < edges.len() && remaining[edges[*pos]] <= Amount::zero() {
+                    *pos += 1;
+                }
+                let edge = *edges.get(*pos).ok_or(Error::UnbalancedSetOff)?;
+                path.push(edge);
+                firm = setoffs[edge].creditor();
+            };
+
+            let members = &path[begin..];
+            let amount = members
+                .iter()
+                .map(|i| remaining[*i])
+                .min()
+                .unwrap_or_default();
+            members.iter().for_each(|i| remaining[*i] -= amount);
+            cycles.push(Cycle {
+                obligations: members.iter().map(|i| setoffs[*i].id()).collect(),
+                firms: members
+                    .iter()
+                    .map(|i| setoffs[*i].debtor().clone())
+                    .collect(),
+                amount,
+                kind: CycleKind::Multilateral,
+            });
+        }
+    }
+
+    Ok(cycles)
+}
+
+/// Greedily clears the debts between pairs of firms along simple cycles of at most `max_len`
+/// firms. Returns the cleared cycles, the remaining debts are left in `debts`.
+///
+/// Firms with an entry in `caps` clear at most that amount in total, what is left of their caps is
+/// left in `caps`.
+///
+/// The debts are visited in the specified `order` and every debt is cleared along the shortest
+/// cycles through it (found by a BFS over the remaining debts) until no cycle that is short enough
+/// is left. This is exact for `max_len == 2`, but only a heuristic otherwise.
+pub(crate) fn clear_short_cycles<AccountId, Amount>(
+    debts: &mut BTreeMap<(AccountId, AccountId), Amount>,
+    order: &[(AccountId, AccountId)],
+    caps: &mut BTreeMap<AccountId, Amount>,
+    max_len: usize,
+) -> Vec<Cycle<AccountId, Amount>>
+where
+    AccountId: Id,
+    Amount: Int,
+{
+    let mut cycles = vec![];
+    if max_len < 2 {
+        return cycles;
+    }
+
+    let outgoing = debts.keys().fold(
+        BTreeMap::<_, Vec<_>>::new(),
+        |mut acc, (debtor, creditor)| {
+            acc.entry(debtor.clone())
+                .or_default()
+                .push(creditor.clone());
+            acc
+        },
+    );
+    for pair in order {
+        while debts[pair] > Amount::zero() && !exhausted(caps, &pair.0) && !exhausted(caps, &pair.1)
+        {
+            // the firms from the creditor back to the debtor
+            let path = match shortest_path(debts, &outgoing, caps, &pair.1, &pair.0, max_len - 1) {
+                Some(path) => path,
+                None => break,
+            };
+            let firms: Vec<_> = path[path.len() - 1..]
+                .iter()
+                .chain(&path[..path.len() - 1])
+                .cloned()
+                .collect();
+            let edges: Vec<_> = firms
+                .iter()
+                .zip(firms.iter().cycle().skip(1))
+                .map(|(debtor, creditor)| (debtor.clone(), creditor.clone()))
+                .collect();
+
+            let amount = edges
+                .iter()
+                .map(|edge| debts[edge])
+                .chain(firms.iter().filter_map(|firm| caps.get(firm).copied()))
+                .min()
+                .unwrap_or_default();
+            edges
+                .iter()
+                .for_each(|edge| *debts.get_mut(edge).expect("edge of cycle") -= amount);
+            firms.iter().for_each(|firm| {
+                if let Some(cap) = caps.get_mut(firm) {
+                    *cap -= amount;
+                }
+            });
+            cycles.push(Cycle {
+                obligations: vec![None; firms.len()],
+                firms,
+                amount,
+                kind: CycleKind::Multilateral,
+            });
+        }
+    }
+
+    cycles
+}
+
+// Checks if a firm has used up its set-off cap
+fn exhausted<AccountId, Amount>(caps: &BTreeMap<AccountId, Amount>, firm: &AccountId) -> bool
+where
+    AccountId: Id,
+    Amount: Int,
+{
+    matches!(caps.get(firm), Some(cap) if *cap <= Amount::zero())
+}
+
+// Find a path with the least number of (positive) debts from `from` to `to`, using at most
+// `max_edges` debts and avoiding firms that have used up their caps
+fn shortest_path<AccountId, Amount>(
+    debts: &BTreeMap<(AccountId, AccountId), Amount>,
+    outgoing: &BTreeMap<AccountId, Vec<AccountId>>,
+    caps: &BTreeMap<AccountId, Amount>,
+    from: &AccountId,
+    to: &AccountId,
+    max_edges: usize,
+) -> Option<Vec<AccountId>>
+where
+    AccountId: Id,
+    Amount: Int,
+{
+    let mut parent = BTreeMap::new();
+    parent.insert(from.clone(), None);
+    let mut frontier = vec![from.clone()];
+
+    for _ in 0..max_edges {
+        let mut next = vec![];
+        for firm in &frontier {
+            for creditor in outgoing.get(firm).into_iter().flatten() {
+                if parent.contains_key(creditor)
+                    || debts[&(firm.clone(), creditor.clone())] <= Amount::zero()
+                    || exhausted(caps, creditor)
+                {
+                    continue;
+                }
+                parent.insert(creditor.clone(), Some(firm.clone()));
+                if creditor == to {
+                    let mut path = vec![to.clone()];
+                    while let Some(Some(prev)) = parent.get(&path[path.len() - 1]) {
+                        path.push(prev.clone());
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                next.push(creditor.clone());
+            }
+        }
+        frontier = next;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setoff::SimpleSetoff;
+
+    fn setoffs(set_offs: &[(u64, u64, i64)]) -> Vec<SimpleSetoff<u64, i64>> {
+        set_offs
+            .iter()
+            .enumerate()
+            .map(|(id, (debtor, creditor, set_off))| {
+                SimpleSetoff::new(Some(id), *debtor, *creditor, 10, *set_off, 10 - set_off)
+            })
+            .collect()
+    }
+
+    // Checks that the cycles are closed and cover the `set_off` of every set-off notice exactly
+    fn assert_decomposition(setoffs: &[SimpleSetoff<u64, i64>], cycles: &[Cycle<u64, i64>]) {
+        for cycle in cycles {
+            assert!(cycle.amount > 0);
+            let members: Vec<_> = cycle
+                .obligations
+                .iter()
+                .map(|id| &setoffs[id.unwrap()])
+                .collect();
+            for (so, next) in members.iter().zip(members.iter().cycle().skip(1)) {
+                assert_eq!(so.creditor, next.debtor);
+            }
+            let firms: Vec<_> = members.iter().map(|so| so.debtor).collect();
+            assert_eq!(cycle.firms, firms);
+        }
+        for so in setoffs {
+            let covered: i64 = cycles
+                .iter()
+                .filter(|cycle| cycle.obligations.contains(&so.id))
+                .map(|cycle| cycle.amount)
+                .sum();
+            assert_eq!(covered, so.set_off);
+        }
+    }
+
+    #[test]
+    fn set_offs_are_decomposed_into_cycles() {
+        // a triangle of 3 and a reciprocal pair of 2 sharing the obligation from 1 to 2
+        let setoffs = setoffs(&[(1, 2, 5), (2, 3, 3), (3, 1, 3), (2, 1, 2), (3, 4, 0)]);
+        let cycles = decompose_cycles(&setoffs).unwrap();
+        assert_decomposition(&setoffs, &cycles);
+        assert_eq!(cycles.len(), 2);
+        assert!(cycles
+            .iter()
+            .all(|cycle| cycle.kind == CycleKind::Multilateral));
+    }
+
+    #[test]
+    fn bilateral_set_offs_are_decomposed_into_pairs() {
+        let setoffs = setoffs(&[(1, 2, 5), (2, 3, 3), (3, 1, 3), (2, 1, 2)]);
+        let cycles = decompose_cycles_with_bilateral(&setoffs, &[2, 0, 0, 2]).unwrap();
+        assert_decomposition(&setoffs, &cycles);
+        let bilateral: Vec<_> = cycles
+            .iter()
+            .filter(|cycle| cycle.kind == CycleKind::Bilateral)
+            .collect();
+        assert_eq!(bilateral.len(), 1);
+        assert_eq!(bilateral[0].obligations, [Some(0), Some(3)]);
+        assert_eq!(bilateral[0].amount, 2);
+    }
+
+    #[test]
+    fn unbalanced_set_offs_are_an_error() {
+        assert!(matches!(
+            decompose_cycles(&setoffs(&[(1, 2, 5), (2, 3, 5), (3, 1, 4)])),
+            Err(Error::UnbalancedSetOff)
+        ));
+        assert!(matches!(
+            decompose_cycles_with_bilateral(&setoffs(&[(1, 2, 2), (2, 1, 2)]), &[2, 1]),
+            Err(Error::UnbalancedSetOff)
+        ));
+    }
+}
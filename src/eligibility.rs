@@ -0,0 +1,30 @@
+use alloc::string::String;
+
+use crate::obligation::Obligation;
+
+/// A predicate that decides which obligations can be set off at all (e.g. to leave out disputed
+/// invoices). Ineligible obligations are still part of the output with a zero set-off.
+pub trait Eligibility<O: Obligation> {
+    /// Returns the reason why `obligation` can't be set off, or `None` if it is eligible.
+    fn ineligible(&self, obligation: &O) -> Option<String>;
+}
+
+impl<O, F> Eligibility<O> for F
+where
+    O: Obligation,
+    F: Fn(&O) -> Option<String>,
+{
+    fn ineligible(&self, obligation: &O) -> Option<String> {
+        self(obligation)
+    }
+}
+
+/// Every obligation is eligible.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AllEligible;
+
+impl<O: Obligation> Eligibility<O> for AllEligible {
+    fn ineligible(&self, _obligation: &O) -> Option<String> {
+        None
+    }
+}
@@ -8,6 +8,50 @@ pub enum Error {
     ObligationToSelf,
     /// Invalid obligation amount, expected positive value
     NonPositiveAmount,
+    /// Invalid obligation priority, expected non-negative value
+    NegativePriority,
+    /// Set-off amounts are not balanced, expected every firm to clear as much as it is cleared
+    UnbalancedSetOff,
+    /// Invalid liquidity, expected non-negative value
+    NegativeLiquidity,
+    /// Allocation overflow, the product of the cleared amount and an obligation amount doesn't fit
+    AllocationOverflow,
+    /// Invalid allocation, expected set-off amounts that sum up to the cleared amount and don't exceed their obligation's amount
+    InvalidAllocation,
+    /// A max cycle length can't be combined with liquidity
+    MaxCycleLengthWithLiquidity,
+    /// Invalid set-off cap, expected non-negative value
+    NegativeCap,
+    /// Indivisible obligations can't be combined with a max cycle length
+    IndivisibleWithMaxCycleLength,
+    /// Infeasible set-off, the indivisible obligations can't be set off entirely or not at all
+    InfeasibleIndivisible,
+    /// Edge cost overflow, the priorities are too high for the total debt
+    CostOverflow,
+    /// Amount overflow, a total of the amounts doesn't fit
+    AmountOverflow,
+    /// Unbalanced {vector} b-vector, expected the positive balances ({positive}) to sum up to the negative balances ({negative})
+    UnbalancedNetPositions {
+        vector: &'static str,
+        positive: String,
+        negative: String,
+    },
+    /// Net position mismatch for firm {firm}, expected the net position before set-off ({before}) to equal the net position after set-off ({after})
+    NetPositionMismatch {
+        firm: String,
+        before: String,
+        after: String,
+    },
+    /// NID mismatch, expected the NID before set-off ({before}) to equal the NID after set-off ({after})
+    NidMismatch { before: String, after: String },
+    /// Set-off inconsistency for firm {firm}, expected the set-off of its debts ({as_debtor}) to equal the set-off of its claims ({as_creditor})
+    InconsistentSetOff {
+        firm: String,
+        as_debtor: String,
+        as_creditor: String,
+    },
+    /// Remainder mismatch, expected the total remainder ({expected}) to equal the sum of the remainders of all set-offs ({actual})
+    RemainderMismatch { expected: String, actual: String },
     /// Max flow algorithm specific error
     AlgoSpecific(String),
 }
@@ -20,6 +20,11 @@ impl<M, O, SO> ComplexIdMtcs<M, O, SO> {
         }
     }
 
+    /// The wrapped MTCS impl, e.g. to get the report of the last run from it.
+    pub fn inner(&self) -> &M {
+        &self.inner
+    }
+
     fn firm_pos<Id: PartialEq>(firms: &mut Vec<Id>, firm: Id) -> usize {
         if let Some(pos) = firms.iter().position(|f| *f == firm) {
             pos
@@ -58,7 +63,10 @@ where
             .map(|o| {
                 let debtor = Self::firm_pos(&mut firms, o.debtor);
                 let creditor = Self::firm_pos(&mut firms, o.creditor);
-                SimpleObligation::new(o.id, debtor, creditor, o.amount).unwrap()
+                SimpleObligation::new(o.id, debtor, creditor, o.amount)
+                    .and_then(|so| so.with_priority(o.priority))
+                    .unwrap()
+                    .with_indivisible(o.indivisible.unwrap_or_default())
             })
             .collect();
 
@@ -74,6 +82,8 @@ where
                     so.set_off,
                     so.remainder,
                 )
+                .with_priority(so.priority)
+                .with_reason(so.reason)
             })
             .collect();
 
@@ -96,6 +106,8 @@ where
                     so.set_off(),
                     so.remainder(),
                 )
+                .with_priority(so.priority())
+                .with_reason(so.reason().map(Into::into))
             })
             .collect();
 
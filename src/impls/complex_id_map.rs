@@ -61,7 +61,10 @@ where
             .map(|o| {
                 let debtor = *firms_mapping.get(o.debtor()).unwrap();
                 let creditor = *firms_mapping.get(o.creditor()).unwrap();
-                SimpleObligation::new(o.id(), debtor, creditor, o.amount()).unwrap()
+                SimpleObligation::new(o.id(), debtor, creditor, o.amount())
+                    .and_then(|so| so.with_priority(o.priority()))
+                    .unwrap()
+                    .with_indivisible(o.indivisible())
             })
             .collect();
 
@@ -84,6 +87,8 @@ where
                     so.set_off(),
                     so.remainder(),
                 )
+                .with_priority(so.priority())
+                .with_reason(so.reason().map(Into::into))
             })
             .collect();
 
@@ -116,6 +121,8 @@ where
                     so.set_off(),
                     so.remainder(),
                 )
+                .with_priority(so.priority())
+                .with_reason(so.reason().map(Into::into))
             })
             .collect();
 
@@ -0,0 +1,54 @@
+use core::cmp::min;
+
+use num_traits::Zero;
+
+use crate::error::Error;
+use crate::id::Id;
+use crate::int::Int;
+use crate::obligation::Obligation;
+use crate::parallel::MaybeSync;
+
+use super::pass::{Pass, State};
+
+// The bilateral pre-pass, which nets the debts of every pair of firms that owe each other (as far
+// as their caps allow) before the min-cost flow is run
+pub(super) struct Bilateral {
+    pub(super) enabled: bool,
+}
+
+impl<O> Pass<O> for Bilateral
+where
+    O: Obligation + MaybeSync,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    fn prepare(&self, state: &mut State<'_, O>) -> Result<(), Error> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        for ((debtor, creditor), amount) in &state.debts {
+            let reverse = match state.debts.get(&(creditor.clone(), debtor.clone())) {
+                Some(reverse) if debtor < creditor => *reverse,
+                _ => continue,
+            };
+            let amount = [debtor, creditor]
+                .iter()
+                .filter_map(|firm| state.caps.get(*firm))
+                .fold(min(*amount, reverse), |acc, cap| min(acc, *cap));
+            for firm in [debtor, creditor] {
+                if let Some(cap) = state.caps.get_mut(firm) {
+                    *cap -= amount;
+                }
+            }
+            state
+                .netted
+                .insert((debtor.clone(), creditor.clone()), amount);
+            state
+                .netted
+                .insert((creditor.clone(), debtor.clone()), amount);
+        }
+        state.netted.retain(|_, amount| *amount > O::Amount::zero());
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+use alloc::collections::BTreeMap;
+use core::cmp::{max, min};
+
+use num_traits::Zero;
+
+use crate::algo::mcmf::EdgeWeight;
+use crate::error::Error;
+use crate::id::Id;
+use crate::int::Int;
+use crate::node::Node;
+use crate::obligation::Obligation;
+use crate::parallel::MaybeSync;
+
+use super::pass::{Network, Pass, State};
+
+// Enforces the caps of the firms (less what they netted bilaterally) by splitting them in the flow
+// network of every component
+pub(super) struct Caps;
+
+impl<O> Pass<O> for Caps
+where
+    O: Obligation + MaybeSync,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    fn network(
+        &self,
+        state: &State<'_, O>,
+        network: &mut Network<O::AccountId, O::Amount>,
+    ) -> Result<(), Error> {
+        // A cap bounds both the debts and the claims of a firm that are discharged (by set-off or
+        // by cash), so it only matters if it's less than its total debt or its total claims. The
+        // remainder of its debts (and of its claims) must then be at least the difference, so the
+        // firm is split into its `Incoming` node (where its claims end), its `WithId` node (where
+        // its cash and net position are) and its `Outgoing` node (where its debts start), with an
+        // edge in between each that carries the remainder.
+        let mut totals = BTreeMap::<_, (O::Amount, O::Amount)>::new();
+        for ((debtor, creditor), debt) in &network.debts {
+            totals.entry(debtor).or_default().0 += *debt;
+            totals.entry(creditor).or_default().1 += *debt;
+        }
+        let split: BTreeMap<_, _> = totals
+            .into_iter()
+            .filter_map(|(firm, (debt, claims))| {
+                let cap = *state.caps.get(firm)?;
+                (cap < max(debt, claims)).then(|| (firm.clone(), (cap, debt, claims)))
+            })
+            .collect();
+
+        // the lower bound on the remainder over an edge is met by sending it from the source
+        // straight to the head of the edge and from its tail straight to the sink, so that at most
+        // the rest (i.e. the cap) is left for the edge itself
+        for (firm, (cap, debt, claims)) in &split {
+            let edges = [
+                (Node::Incoming(firm.clone()), firm.into(), *claims),
+                (firm.into(), Node::Outgoing(firm.clone()), *debt),
+            ];
+            for (tail, head, total) in edges {
+                let capacity = min(*cap, total);
+                if capacity > O::Amount::zero() {
+                    network.graph.insert(
+                        (tail.clone(), head.clone()),
+                        EdgeWeight {
+                            cost: O::Amount::zero(),
+                            capacity,
+                        },
+                    );
+                }
+                let lower = total - capacity;
+                if lower > O::Amount::zero() {
+                    for edge in [(Node::Source, head), (tail, Node::Sink)] {
+                        network
+                            .graph
+                            .entry(edge)
+                            .or_insert_with(|| EdgeWeight {
+                                cost: O::Amount::zero(),
+                                capacity: O::Amount::zero(),
+                            })
+                            .capacity += lower;
+                    }
+                }
+            }
+        }
+
+        network.split.extend(split.into_keys());
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use petgraph::algo::tarjan_scc;
+use petgraph::graphmap::DiGraphMap;
+
+use crate::error::Error;
+use crate::id::Id;
+use crate::int::Int;
+use crate::obligation::Obligation;
+use crate::parallel::MaybeSync;
+
+use super::pass::{Pass, State};
+
+// Splits the obligations that are left after the bilateral pre-pass into the strongly connected
+// components of the firm graph, so that every component can be solved on its own. Cash can
+// discharge obligations that aren't on any cycle, so with liquidity (i.e. unless `prune`) they
+// are all solved as one component.
+pub(super) struct Components {
+    pub(super) prune: bool,
+}
+
+impl<O> Pass<O> for Components
+where
+    O: Obligation + MaybeSync,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    fn prepare(&self, state: &mut State<'_, O>) -> Result<(), Error> {
+        let outstanding = state
+            .included
+            .iter()
+            .map(|i| (*i, &state.on[*i]))
+            .filter(|(_, o)| {
+                let pair = (o.debtor().clone(), o.creditor().clone());
+                state.netted.get(&pair).copied().unwrap_or_default() < state.debts[&pair]
+            });
+        state.components = match self.prune {
+            true => strongly_connected_components(outstanding),
+            false => vec![outstanding.map(|(i, _)| i).collect()],
+        };
+        log::debug!("Components = {}", state.components.len());
+        Ok(())
+    }
+}
+
+// Group the obligations (by their index) by the strongly connected component of the firm graph that
+// they lie in. Obligations between different components aren't on any cycle, so they are left out.
+fn strongly_connected_components<'a, O>(
+    on: impl Iterator<Item = (usize, &'a O)> + Clone,
+) -> Vec<Vec<usize>>
+where
+    O: Obligation + 'a,
+    O::AccountId: Id,
+{
+    let firms = on
+        .clone()
+        .flat_map(|(_, o)| [o.debtor(), o.creditor()])
+        .fold(BTreeMap::new(), |mut acc, firm| {
+            let len = acc.len();
+            acc.entry(firm).or_insert(len);
+            acc
+        });
+    let graph = DiGraphMap::<usize, ()>::from_edges(
+        on.clone()
+            .map(|(_, o)| (firms[o.debtor()], firms[o.creditor()])),
+    );
+
+    let mut component = vec![0; firms.len()];
+    for (c, nodes) in tarjan_scc(&graph).into_iter().enumerate() {
+        nodes.into_iter().for_each(|node| component[node] = c);
+    }
+
+    on.fold(BTreeMap::<_, Vec<_>>::new(), |mut acc, (i, o)| {
+        let c = component[firms[o.debtor()]];
+        if c == component[firms[o.creditor()]] {
+            acc.entry(c).or_default().push(i);
+        }
+        acc
+    })
+    .into_values()
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obligation::SimpleObligation;
+
+    #[test]
+    fn obligations_between_components_are_pruned() {
+        // the cycles {1, 2} and {3, 4, 5} are bridged by 2 -> 3, and 5 -> 6 leads out of them
+        let on: Vec<_> = [(1, 2), (2, 1), (2, 3), (3, 4), (4, 5), (5, 3), (5, 6)]
+            .into_iter()
+            .map(|(debtor, creditor)| SimpleObligation::<u64, i64>::new(None, debtor, creditor, 1))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let mut components = strongly_connected_components(on.iter().enumerate());
+        components.sort();
+        assert_eq!(components, [vec![0, 1], vec![3, 4, 5]]);
+    }
+}
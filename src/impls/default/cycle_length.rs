@@ -0,0 +1,179 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min, Reverse};
+use core::mem;
+
+use num_traits::Zero;
+
+use crate::cycle::{clear_short_cycles, decompose_cycles, Cycle, CycleKind};
+use crate::error::Error;
+use crate::id::Id;
+use crate::int::Int;
+use crate::obligation::Obligation;
+use crate::parallel::MaybeSync;
+use crate::setoff::{SetOff, SimpleSetoff};
+
+use super::pass::{Pairs, Pass, State};
+use super::reduced_debts;
+
+// Only sets off along cycles of at most `max_len` firms (if any). Every simple cycle of a component
+// with at most `max_len` firms is short enough, so only the larger components are cleared greedily
+// instead of by the min-cost flow. The cycles that are cleared are kept track of.
+pub(super) struct MaxCycleLength {
+    pub(super) max_len: Option<usize>,
+}
+
+impl<O> Pass<O> for MaxCycleLength
+where
+    O: Obligation + MaybeSync,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    fn prepare(&self, state: &mut State<'_, O>) -> Result<(), Error> {
+        if let Some(max_len) = self.max_len {
+            let (components, greedy) = mem::take(&mut state.components)
+                .into_iter()
+                .partition(|component| firm_count(&state.obligations(component)) <= max_len);
+            state.components = components;
+            state.greedy = greedy;
+        }
+        Ok(())
+    }
+
+    fn finish(&self, state: &mut State<'_, O>) -> Result<(), Error> {
+        let max_len = match self.max_len {
+            Some(max_len) => max_len,
+            None => return Ok(()),
+        };
+
+        // the cycles that are cleared between pairs of firms
+        state.cycles.extend(
+            state
+                .netted
+                .iter()
+                .filter(|((debtor, creditor), _)| debtor < creditor)
+                .map(|((debtor, creditor), amount)| Cycle {
+                    obligations: vec![None; 2],
+                    firms: vec![debtor.clone(), creditor.clone()],
+                    amount: *amount,
+                    kind: CycleKind::Bilateral,
+                }),
+        );
+        let flows: Vec<_> = state
+            .cleared
+            .iter()
+            .map(|((debtor, creditor), amount)| {
+                SimpleSetoff::new(
+                    None,
+                    debtor.clone(),
+                    creditor.clone(),
+                    *amount,
+                    *amount,
+                    O::Amount::zero(),
+                )
+            })
+            .collect();
+        state.cycles.extend(decompose_cycles(&flows)?);
+
+        for component in mem::take(&mut state.greedy) {
+            let component = state.obligations(&component);
+            let mut debts = reduced_debts(&component, &state.netted);
+            let priorities = priorities(&component);
+            let mut order: Vec<_> = debts.keys().cloned().collect();
+            order.sort_by_key(|pair| Reverse(priorities[pair]));
+            for cycle in clear_short_cycles(&mut debts, &order, &mut state.caps, max_len) {
+                for pair in cycle.firms.iter().zip(cycle.firms.iter().cycle().skip(1)) {
+                    *state
+                        .cleared
+                        .entry((pair.0.clone(), pair.1.clone()))
+                        .or_default() += cycle.amount;
+                }
+                state.cycles.push(cycle);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Calculate the priority-of-claims of every pair of firms, i.e. the highest priority among their
+// obligations (which is the order in which the pairs are cleared greedily)
+fn priorities<O>(on: &[&O]) -> Pairs<O::AccountId, O::Amount>
+where
+    O: Obligation,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    on.iter().fold(BTreeMap::new(), |mut acc, o| {
+        let priority = acc
+            .entry((o.debtor().clone(), o.creditor().clone()))
+            .or_insert_with(O::Amount::zero);
+        *priority = max(*priority, o.priority().unwrap_or_default());
+        acc
+    })
+}
+
+// Count the firms that are party to the obligations `on`
+fn firm_count<O>(on: &[&O]) -> usize
+where
+    O: Obligation,
+    O::AccountId: Id,
+{
+    on.iter()
+        .flat_map(|o| [o.debtor(), o.creditor()])
+        .collect::<BTreeSet<_>>()
+        .len()
+}
+
+// Split the cycles between pairs of firms into cycles between their obligations, using up the
+// set-offs of the obligations of every pair (in the order of `pairs`)
+pub(super) fn split_cycles<O>(
+    on: &[O],
+    pairs: &BTreeMap<(O::AccountId, O::AccountId), Vec<usize>>,
+    set_offs: &[O::Amount],
+    cycles: Vec<Cycle<O::AccountId, O::Amount>>,
+) -> Vec<Cycle<O::AccountId, O::Amount>>
+where
+    O: Obligation,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    let mut remaining = set_offs.to_vec();
+    let mut next: BTreeMap<_, usize> = pairs.keys().map(|pair| (pair, 0)).collect();
+
+    let mut split = vec![];
+    for cycle in cycles {
+        let edges: Vec<_> = cycle
+            .firms
+            .iter()
+            .zip(cycle.firms.iter().cycle().skip(1))
+            .map(|(debtor, creditor)| (debtor.clone(), creditor.clone()))
+            .collect();
+
+        let mut amount = cycle.amount;
+        while amount > O::Amount::zero() {
+            let members: Vec<_> = edges
+                .iter()
+                .map(|edge| {
+                    let idxs = &pairs[edge];
+                    let pos = next.get_mut(edge).expect("pair of obligations");
+                    while remaining[idxs[*pos]] <= O::Amount::zero() {
+                        *pos += 1;
+                    }
+                    idxs[*pos]
+                })
+                .collect();
+            let step = members.iter().map(|i| remaining[*i]).fold(amount, min);
+            members.iter().for_each(|i| remaining[*i] -= step);
+            split.push(Cycle {
+                obligations: members.iter().map(|i| on[*i].id()).collect(),
+                firms: cycle.firms.clone(),
+                amount: step,
+                kind: cycle.kind,
+            });
+            amount -= step;
+        }
+    }
+
+    split
+}
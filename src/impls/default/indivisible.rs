@@ -0,0 +1,292 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+use num_traits::Zero;
+
+use crate::error::Error;
+use crate::id::Id;
+use crate::int::Int;
+use crate::obligation::Obligation;
+
+use super::Cleared;
+
+/// The max number of indivisible obligations for which the best set-off is searched exhaustively
+/// (by branch and bound) by default.
+pub(super) const MAX_EXACT_INDIVISIBLE: usize = 16;
+
+// Searches the ways of setting off the indivisible obligations at the `included` indices of `on`
+// entirely or not at all for the one that clears the most, where `clear` runs a clearing pass with
+// some of them decided (see `DefaultMtcs::clear_pass`). Every branch is bounded by the clearing
+// where the undecided obligations are divisible, which is only admissible without a bilateral
+// pre-pass (that can clear more when some obligations are left out). Once the algo is cancelled
+// (e.g. by a time limit), the best set-off found so far is returned, or the obligations that don't
+// fit are left out as by `round_down` if none was found.
+pub(super) fn branch_and_bound<O>(
+    on: &[O],
+    included: &BTreeSet<usize>,
+    relaxed: Cleared<O>,
+    mut clear: impl FnMut(&BTreeMap<usize, bool>) -> Result<Option<Cleared<O>>, Error>,
+) -> Result<Cleared<O>, Error>
+where
+    O: Obligation,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    let mut best: Option<Cleared<O>> = None;
+    let mut branches = vec![(BTreeMap::new(), Some(relaxed))];
+    let mut cancelled = false;
+    while let Some((decided, cleared)) = branches.pop() {
+        let cleared = match cleared {
+            Some(cleared) => cleared,
+            None => match clear(&decided)? {
+                Some(cleared) => cleared,
+                None => continue,
+            },
+        };
+        if cleared.cancelled {
+            cancelled = true;
+            break;
+        }
+        let bound = cleared.total();
+        if matches!(&best, Some(best) if bound <= best.total()) {
+            continue;
+        }
+
+        // branch on an obligation that didn't fit, or on any undecided one if the completed
+        // decisions turn out to clear less than the bound
+        let branch = match complete(on, included, &decided, &cleared) {
+            Ok(completed) => match clear(&completed)? {
+                Some(cleared) if cleared.total() == bound => {
+                    best = Some(cleared);
+                    continue;
+                }
+                _ => undecided(on, included, &decided),
+            },
+            Err(misfit) => Some(misfit),
+        };
+        if let Some(i) = branch {
+            for set_off in [false, true] {
+                let mut decided = decided.clone();
+                decided.insert(i, set_off);
+                branches.push((decided, None));
+            }
+        }
+    }
+
+    match best {
+        Some(best) => Ok(best),
+        None if cancelled => {
+            let relaxed = clear(&BTreeMap::new())?.ok_or(Error::InfeasibleIndivisible)?;
+            round_down(on, included, relaxed, clear)
+        }
+        // leaving out all indivisible obligations is always possible
+        None => Err(Error::InfeasibleIndivisible),
+    }
+}
+
+// Leaves out the indivisible obligations that don't fit one after the other (largest first)
+// until the remaining ones can all be set off entirely or not at all
+pub(super) fn round_down<O>(
+    on: &[O],
+    included: &BTreeSet<usize>,
+    relaxed: Cleared<O>,
+    mut clear: impl FnMut(&BTreeMap<usize, bool>) -> Result<Option<Cleared<O>>, Error>,
+) -> Result<Cleared<O>, Error>
+where
+    O: Obligation,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    let mut decided = BTreeMap::new();
+    let mut cleared = relaxed;
+    loop {
+        let misfit = match complete(on, included, &decided, &cleared) {
+            Ok(completed) => {
+                if let Some(cleared) = clear(&completed)? {
+                    return Ok(cleared);
+                }
+                undecided(on, included, &decided)
+            }
+            Err(misfit) => Some(misfit),
+        };
+        decided.insert(misfit.ok_or(Error::InfeasibleIndivisible)?, false);
+        cleared = clear(&decided)?.ok_or(Error::InfeasibleIndivisible)?;
+    }
+}
+
+// Complete the `decided` indivisible obligations if the amount `cleared` between every pair of
+// firms can be made up of entire indivisible obligations (the largest ones that fit first) and a
+// part of the divisible ones. Otherwise return the largest indivisible obligation that didn't fit.
+fn complete<O>(
+    on: &[O],
+    included: &BTreeSet<usize>,
+    decided: &BTreeMap<usize, bool>,
+    cleared: &Cleared<O>,
+) -> Result<BTreeMap<usize, bool>, usize>
+where
+    O: Obligation,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    let pairs = included
+        .iter()
+        .filter(|i| decided.get(i) != Some(&false))
+        .fold(BTreeMap::<_, Vec<_>>::new(), |mut acc, i| {
+            acc.entry((on[*i].debtor().clone(), on[*i].creditor().clone()))
+                .or_default()
+                .push(*i);
+            acc
+        });
+
+    let mut completed = decided.clone();
+    let mut misfit: Option<usize> = None;
+    for (pair, mut idxs) in pairs {
+        let mut left = cleared.pair(&pair);
+        let mut divisible = O::Amount::zero();
+        idxs.sort_by_key(|i| Reverse(on[*i].amount()));
+        let mut misfits = vec![];
+        for i in idxs {
+            let amount = on[i].amount();
+            match decided.get(&i) {
+                _ if !on[i].indivisible() => divisible += amount,
+                Some(_) => left -= amount,
+                None if amount <= left => {
+                    left -= amount;
+                    completed.insert(i, true);
+                }
+                None => {
+                    completed.insert(i, false);
+                    misfits.push(i);
+                }
+            }
+        }
+        if left > divisible {
+            misfit = misfits
+                .into_iter()
+                .chain(misfit)
+                .max_by_key(|i| on[*i].amount());
+        }
+    }
+
+    match misfit {
+        Some(i) => Err(i),
+        None => Ok(completed),
+    }
+}
+
+// Find the largest indivisible obligation that isn't `decided` yet
+fn undecided<O>(
+    on: &[O],
+    included: &BTreeSet<usize>,
+    decided: &BTreeMap<usize, bool>,
+) -> Option<usize>
+where
+    O: Obligation,
+    O::Amount: Int,
+{
+    included
+        .iter()
+        .copied()
+        .filter(|i| on[*i].indivisible() && !decided.contains_key(i))
+        .max_by_key(|i| on[*i].amount())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use alloc::collections::BTreeMap;
+
+    use crate::algo::mcmf::primal_dual::PrimalDual;
+    use crate::impls::default::DefaultMtcs;
+    use crate::obligation::{Obligation, SimpleObligation};
+    use crate::setoff::SimpleSetoff;
+    use crate::testing::Rng;
+    use crate::Mtcs;
+
+    // Small pseudo-random networks of indivisible obligations between the firms `0..4`
+    fn networks() -> impl Iterator<Item = Vec<SimpleObligation<u64, i64>>> {
+        let mut rng = Rng::new(0x1234_5678_9abc_def1);
+        let mut next = move |n: u64| rng.below(n);
+        (0..200).map(move |_| {
+            (0..4 + next(10))
+                .map(|id| (id as usize, next(4), next(4), 1 + next(4) as i64))
+                .filter(|(_, debtor, creditor, _)| debtor != creditor)
+                .map(|(id, debtor, creditor, amount)| {
+                    SimpleObligation::new(Some(id), debtor, creditor, amount)
+                        .unwrap()
+                        .with_indivisible(true)
+                })
+                .collect()
+        })
+    }
+
+    // The most that can be cleared by trying every subset of the obligations that leaves the net
+    // positions of all firms unchanged
+    fn brute_force(on: &[SimpleObligation<u64, i64>]) -> i64 {
+        (0..1_u32 << on.len())
+            .filter_map(|subset| {
+                let mut balance = [0; 4];
+                let mut cleared = 0;
+                for (i, o) in on.iter().enumerate() {
+                    if subset & (1 << i) != 0 {
+                        balance[*o.debtor() as usize] -= o.amount();
+                        balance[*o.creditor() as usize] += o.amount();
+                        cleared += o.amount();
+                    }
+                }
+                balance.iter().all(|b| *b == 0).then_some(cleared)
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn branch_and_bound_clears_as_much_as_brute_force() {
+        for on in networks() {
+            let expected = brute_force(&on);
+            let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default());
+            let setoffs = mtcs.run(on.clone()).unwrap();
+            for so in &setoffs {
+                assert!(so.set_off == 0 || so.set_off == so.amount);
+            }
+            assert_eq!(setoffs.iter().map(|so| so.set_off).sum::<i64>(), expected);
+        }
+    }
+
+    #[test]
+    fn rounding_down_clears_at_most_as_much_as_branch_and_bound() {
+        let mut less = 0;
+        for on in networks() {
+            let expected = brute_force(&on);
+            let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default())
+                .with_max_exact_indivisible(0);
+            let setoffs = mtcs.run(on.clone()).unwrap();
+            mtcs.check(&setoffs).unwrap();
+            for so in &setoffs {
+                assert!(so.set_off == 0 || so.set_off == so.amount);
+            }
+            let cleared = setoffs.iter().map(|so| so.set_off).sum::<i64>();
+            assert!(cleared <= expected);
+            if cleared < expected {
+                less += 1;
+            }
+        }
+        assert!(less > 0);
+    }
+
+    #[test]
+    fn cancelled_searches_return_a_feasible_set_off() {
+        for on in networks() {
+            let algo = PrimalDual::default().with_progress(|_, _| false);
+            let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(algo);
+            let clearing = mtcs.run_with_liquidity(on, &BTreeMap::new()).unwrap();
+            mtcs.check(&clearing.setoffs).unwrap();
+            for so in &clearing.setoffs {
+                assert!(so.set_off == 0 || so.set_off == so.amount);
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use alloc::collections::BTreeMap;
+
+use num_traits::Zero;
+
+use crate::algo::mcmf::EdgeWeight;
+use crate::error::Error;
+use crate::id::Id;
+use crate::int::Int;
+use crate::node::Node;
+use crate::obligation::Obligation;
+use crate::parallel::MaybeSync;
+
+use super::pass::{Network, Pass, State};
+
+// Lets the firms pay cash into the liquidity pool, which can pay it out to any firm, so that the
+// remaining debt can be routed through the pool instead of over obligations
+pub(super) struct Liquidity<'a, AccountId, Amount> {
+    pub(super) liquidity: &'a BTreeMap<AccountId, Amount>,
+}
+
+impl<O> Pass<O> for Liquidity<'_, O::AccountId, O::Amount>
+where
+    O: Obligation + MaybeSync,
+    O::AccountId: Id + MaybeSync,
+    O::Amount: Int + MaybeSync,
+{
+    fn network(
+        &self,
+        _state: &State<'_, O>,
+        network: &mut Network<O::AccountId, O::Amount>,
+    ) -> Result<(), Error> {
+        let total_cash: O::Amount = self
+            .liquidity
+            .iter()
+            .filter(|(firm, _)| network.net_position.contains_key(*firm))
+            .map(|(_, cash)| *cash)
+            .sum();
+        if total_cash <= O::Amount::zero() {
+            return Ok(());
+        }
+
+        for (firm, cash) in self.liquidity {
+            if network.net_position.contains_key(firm) && *cash > O::Amount::zero() {
+                network.graph.insert(
+                    (firm.into(), Node::Liquidity),
+                    EdgeWeight {
+                        cost: O::Amount::zero(),
+                        capacity: *cash,
+                    },
+                );
+            }
+        }
+        for firm in network.net_position.keys() {
+            network.graph.insert(
+                (Node::Liquidity, firm.into()),
+                EdgeWeight {
+                    cost: O::Amount::zero(),
+                    capacity: total_cash,
+                },
+            );
+        }
+        Ok(())
+    }
+}
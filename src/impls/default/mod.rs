@@ -0,0 +1,1408 @@
+mod bilateral;
+mod caps;
+mod components;
+mod cycle_length;
+mod indivisible;
+mod liquidity;
+mod pass;
+mod priority;
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min, Ordering, Reverse};
+use core::marker::PhantomData;
+use core::mem;
+use core::time::Duration;
+
+use num_traits::{CheckedAdd, CheckedMul, Zero};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::algo::mcmf::{EdgeWeight, FlowGraph, MinCostFlow};
+use crate::allocation::{AllocationPolicy, InputOrder};
+use crate::cycle::Cycle;
+use crate::eligibility::{AllEligible, Eligibility};
+use crate::error::Error;
+use crate::id::Id;
+use crate::int::Int;
+use crate::node::Node;
+use crate::obligation::Obligation;
+use crate::parallel::{MaybeSend, MaybeSync};
+use crate::payment::{payments_from_net_positions, Clearing, Payment};
+use crate::report::{RunReport, Stopwatch, Timings};
+use crate::setoff::SetOff;
+use crate::Mtcs;
+
+use bilateral::Bilateral;
+use caps::Caps;
+use components::Components;
+use cycle_length::{split_cycles, MaxCycleLength};
+use indivisible::{branch_and_bound, round_down, MAX_EXACT_INDIVISIBLE};
+use liquidity::Liquidity;
+use pass::{Network, Pairs, Pass, State};
+use priority::Priorities;
+
+#[derive(Clone, Debug)]
+pub struct DefaultMtcs<O: Obligation, SO, Algo, Policy = InputOrder, Elig = AllEligible> {
+    algo: Algo,
+    policy: Policy,
+    eligibility: Elig,
+    bilateral: bool,
+    max_cycle_length: Option<usize>,
+    max_exact_indivisible: usize,
+    caps: BTreeMap<O::AccountId, O::Amount>,
+    excluded: BTreeSet<O::AccountId>,
+    clock: Option<fn() -> Duration>,
+    /// The number of min-cost flow iterations of the current clearing
+    solver_iterations: usize,
+    /// Whether a min-cost flow run of the current clearing was cancelled
+    cancelled: bool,
+    /// The statistics of the last clearing
+    report: Option<RunReport<O::Amount>>,
+    _phantom: PhantomData<(O, SO)>,
+}
+
+impl<O: Obligation, SO, Algo> DefaultMtcs<O, SO, Algo> {
+    pub fn new(algo: Algo) -> Self {
+        Self::with_allocation_policy(algo, InputOrder)
+    }
+}
+
+impl<O: Obligation, SO, Algo, Policy> DefaultMtcs<O, SO, Algo, Policy> {
+    /// Uses `policy` to split the amount cleared between a pair of firms among their obligations.
+    pub fn with_allocation_policy(algo: Algo, policy: Policy) -> Self {
+        Self {
+            algo,
+            policy,
+            eligibility: AllEligible,
+            bilateral: false,
+            max_cycle_length: None,
+            max_exact_indivisible: MAX_EXACT_INDIVISIBLE,
+            caps: BTreeMap::new(),
+            excluded: BTreeSet::new(),
+            clock: None,
+            solver_iterations: 0,
+            cancelled: false,
+            report: None,
+            _phantom: Default::default(),
+        }
+    }
+}
+
+impl<O: Obligation, SO, Algo, Policy, Elig> DefaultMtcs<O, SO, Algo, Policy, Elig> {
+    /// Only sets off the obligations that `eligibility` accepts. The set-off notices of the other
+    /// obligations have a zero set-off and state the reason why they weren't eligible.
+    pub fn with_eligibility<E>(self, eligibility: E) -> DefaultMtcs<O, SO, Algo, Policy, E> {
+        DefaultMtcs {
+            algo: self.algo,
+            policy: self.policy,
+            eligibility,
+            bilateral: self.bilateral,
+            max_cycle_length: self.max_cycle_length,
+            max_exact_indivisible: self.max_exact_indivisible,
+            caps: self.caps,
+            excluded: self.excluded,
+            clock: self.clock,
+            solver_iterations: self.solver_iterations,
+            cancelled: self.cancelled,
+            report: self.report,
+            _phantom: Default::default(),
+        }
+    }
+
+    /// Nets the debts of every pair of firms that owe each other (i.e. every 2-cycle) before the
+    /// min-cost flow is run, so that the algo only has to clear the reduced network.
+    pub fn with_bilateral_netting(mut self) -> Self {
+        self.bilateral = true;
+        self
+    }
+
+    /// Only sets off along cycles of at most `max_len` firms. Components of the firm graph with
+    /// more than `max_len` firms are cleared greedily (which is exact for `max_len == 2`), so the
+    /// set-off isn't guaranteed to be maximal. Can't be combined with liquidity.
+    pub fn with_max_cycle_length(mut self, max_len: usize) -> Self {
+        self.max_cycle_length = Some(max_len);
+        self
+    }
+
+    /// Searches the best set-off of up to `max` indivisible obligations exhaustively (see
+    /// `run_with_liquidity`), which can take about `2^(max + 1)` min-cost flow runs. Defaults to 16.
+    pub fn with_max_exact_indivisible(mut self, max: usize) -> Self {
+        self.max_exact_indivisible = max;
+        self
+    }
+
+    /// Limits the total set-off of the firms in `caps`, i.e. the total amount of their debts (and
+    /// equally of their claims) that is set off. With liquidity, the amounts of their debts and of
+    /// their claims that are discharged (by set-off or by cash) are limited each. The caps are
+    /// enforced in the flow network by splitting the capped firms into the nodes where their claims
+    /// end, where their cash is and where their debts start.
+    pub fn with_caps(mut self, caps: BTreeMap<O::AccountId, O::Amount>) -> Self {
+        self.caps = caps;
+        self
+    }
+
+    /// Excludes the firms in `excluded` from the clearing, i.e. none of their obligations is set
+    /// off.
+    pub fn with_excluded(mut self, excluded: BTreeSet<O::AccountId>) -> Self {
+        self.excluded = excluded;
+        self
+    }
+
+    /// Measures the phases of every run (see `RunReport::timings`) with `now`, which returns the
+    /// time elapsed since some fixed point in time (e.g. `std::time::Instant` based).
+    pub fn with_clock(mut self, now: fn() -> Duration) -> Self {
+        self.clock = Some(now);
+        self
+    }
+
+    /// The statistics of the last successful run, which is how they can be had from `Mtcs::run`
+    /// (see `Clearing::report` for `run_with_liquidity`).
+    pub fn last_report(&self) -> Option<&RunReport<O::Amount>> {
+        self.report.as_ref()
+    }
+}
+
+impl<O, SO, Algo, Policy, Elig> DefaultMtcs<O, SO, Algo, Policy, Elig>
+where
+    O: Obligation + MaybeSync,
+    O::AccountId: Id + MaybeSend + MaybeSync,
+    O::Amount: Int + CheckedAdd + CheckedMul + MaybeSend + MaybeSync,
+    SO: SetOff<Amount = O::Amount, AccountId = O::AccountId>,
+    Algo: MinCostFlow<
+            GraphIter = FlowGraph<O::AccountId, O::Amount, O::Amount>,
+            EdgeCapacity = O::Amount,
+            EdgeCost = O::Amount,
+        > + Clone
+        + MaybeSend,
+    <Algo as MinCostFlow>::Paths: IntoIterator<Item = ((O::AccountId, O::AccountId), O::Amount)>,
+    Policy: AllocationPolicy<O>,
+    Elig: Eligibility<O>,
+{
+    /// Runs MTCS on `on`, augmented with the cash that the firms in `liquidity` can contribute.
+    ///
+    /// The cash is paid into a pool (`Node::Liquidity`) that can pay it out to any firm, so that
+    /// the total debt discharged (by set-off or by cash) is maximised. The `set_off` amounts of the
+    /// returned set-off notices include what is discharged by cash, and the cash payments that the
+    /// firms have to make are returned alongside. Without any liquidity, this is the same as
+    /// `Mtcs::run`.
+    ///
+    /// Without any liquidity, only obligations on a cycle can be set off, so the obligations
+    /// between different strongly connected components of the firm graph are pruned and every
+    /// component is solved on its own.
+    ///
+    /// With bilateral netting (see `with_bilateral_netting`), the part of every set-off that was
+    /// netted bilaterally is returned in `Clearing::bilateral`. With a max cycle length (see
+    /// `with_max_cycle_length`), the cycles that the set-offs were built from are returned in
+    /// `Clearing::cycles`.
+    ///
+    /// Indivisible obligations are either set off entirely or not at all. With up to
+    /// `with_max_exact_indivisible` of them and without bilateral netting, the best such set-off is
+    /// found by branch and bound over the min-cost flows where some of them are left out or have to
+    /// be set off, until the algo is cancelled. Otherwise the ones that don't fit are left out one
+    /// after the other. What is lost compared with setting them off partially is returned in
+    /// `Clearing::lost`.
+    ///
+    /// The modes are applied in a fixed order: bilateral netting comes first (so that only the rest
+    /// is split into components), the caps are enforced before the debts are costed by priority
+    /// and the liquidity is added last.
+    ///
+    /// If the algo was cancelled (see `MinCostFlow::cancelled`), the set-off is still feasible but
+    /// might not clear as much as possible, which is flagged by `Clearing::optimal`.
+    ///
+    /// The statistics of the run are returned in `Clearing::report`.
+    pub fn run_with_liquidity(
+        &mut self,
+        on: Vec<O>,
+        liquidity: &BTreeMap<O::AccountId, O::Amount>,
+    ) -> Result<Clearing<SO, O::AccountId, O::Amount>, Error> {
+        let mut stopwatch = Stopwatch::start(self.clock);
+        self.solver_iterations = 0;
+        self.cancelled = false;
+        self.report = None;
+
+        if liquidity.values().any(|cash| *cash < O::Amount::zero()) {
+            return Err(Error::NegativeLiquidity);
+        }
+        if self.caps.values().any(|cap| *cap < O::Amount::zero()) {
+            return Err(Error::NegativeCap);
+        }
+        if on
+            .iter()
+            .any(|o| o.priority().unwrap_or_default() < O::Amount::zero())
+        {
+            return Err(Error::NegativePriority);
+        }
+
+        // calculate the b vector
+        let net_position = net_positions(on.iter());
+
+        // calculate Net Internal Debt (NID) from the b vector
+        let nid: O::Amount = net_position
+            .values()
+            .copied()
+            .filter(|balance| balance > &O::Amount::zero())
+            .sum();
+
+        // calculate total debt
+        let td: O::Amount = on.iter().map(|o| o.amount()).sum();
+
+        // ineligible obligations and the obligations of excluded firms are never set off
+        let reasons: Vec<_> = on.iter().map(|o| self.eligibility.ineligible(o)).collect();
+        let included: BTreeSet<_> = on
+            .iter()
+            .enumerate()
+            .filter(|(i, o)| {
+                reasons[*i].is_none()
+                    && !self.excluded.contains(o.debtor())
+                    && !self.excluded.contains(o.creditor())
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        // cash can discharge obligations that aren't on any cycle, so the network can only be split
+        // up if there is no liquidity
+        let has_cash = liquidity
+            .iter()
+            .any(|(firm, cash)| net_position.contains_key(firm) && *cash > O::Amount::zero());
+        if has_cash && self.max_cycle_length.is_some() {
+            return Err(Error::MaxCycleLengthWithLiquidity);
+        }
+        let indivisible = included.iter().filter(|i| on[**i].indivisible()).count();
+        if indivisible > 0 && self.max_cycle_length.is_some() {
+            return Err(Error::IndivisibleWithMaxCycleLength);
+        }
+        // (the branch and bound isn't exact with the bilateral pre-pass)
+        let exact = indivisible <= self.max_exact_indivisible && !self.bilateral;
+        let preparation = stopwatch.lap();
+
+        // the divisible optimum, i.e. the clearing if all obligations could be set off partially
+        let mut clear = |decided: &BTreeMap<_, _>| {
+            self.clear_pass(&on, &included, decided, has_cash, liquidity)
+        };
+        let relaxed = clear(&BTreeMap::new())?.ok_or(Error::InfeasibleIndivisible)?;
+        let divisible_total = relaxed.total();
+
+        // decide which indivisible obligations are set off entirely (and which not at all)
+        let cleared = if indivisible == 0 {
+            relaxed
+        } else if exact {
+            branch_and_bound(&on, &included, relaxed, clear)?
+        } else {
+            round_down(&on, &included, relaxed, clear)?
+        };
+        // (the bilateral pre-pass can clear more when some obligations are left out)
+        let lost = max(divisible_total - cleared.total(), O::Amount::zero());
+        let clearing = stopwatch.lap();
+        let Cleared {
+            decided,
+            netted,
+            multilateral: mut liabilities,
+            cycles,
+            ..
+        } = cleared;
+
+        let mc: O::Amount = liabilities.values().copied().sum();
+        let bc: O::Amount = netted.values().copied().sum();
+        for (pair, amount) in &netted {
+            *liabilities.entry(pair.clone()).or_default() += *amount;
+        }
+        let tc = bc + mc;
+        let remained = td - tc;
+
+        // Print key results and check for correct sums
+        log::info!("----------------------------------");
+        log::info!("            NID = {nid:?}");
+        log::info!("     Total debt = {td:?}");
+        log::info!("Total remainder = {remained:?}");
+        log::info!("  Total cleared = {tc:?}");
+        log::info!("      Bilateral = {bc:?}");
+        log::info!("   Multilateral = {mc:?}");
+        let ineligible = reasons.iter().flatten().count();
+        log::info!("     Ineligible = {ineligible}");
+        log::info!("    Indivisible = {indivisible}");
+        log::info!("           Lost = {lost:?}");
+        let optimal = !self.cancelled;
+        log::info!("        Optimal = {optimal}");
+
+        // group the obligations that can be set off by pair (in input order) and by decreasing
+        // priority within a pair, with the indivisible ones that are set off entirely first
+        let forced = |i: &usize| decided.get(i).copied().unwrap_or_default();
+        let mut pairs = on
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| included.contains(i) && decided.get(i) != Some(&false))
+            .fold(BTreeMap::<_, Vec<_>>::new(), |mut acc, (i, o)| {
+                acc.entry((o.debtor().clone(), o.creditor().clone()))
+                    .or_default()
+                    .push(i);
+                acc
+            });
+
+        // Assign the cleared amount of every pair to its obligations, in order of decreasing
+        // priority. The cleared amount is split among obligations with the same priority according
+        // to the allocation policy.
+        let mut set_offs = vec![O::Amount::zero(); on.len()];
+        let mut bilateral = vec![O::Amount::zero(); on.len()];
+        for (pair, idxs) in &mut pairs {
+            let mut cleared = liabilities.get(pair).copied().unwrap_or_default();
+            let mut netted = netted.get(pair).copied().unwrap_or_default();
+            idxs.sort_by_key(|i| (!forced(i), Reverse(on[*i].priority().unwrap_or_default())));
+
+            let mut start = idxs.iter().take_while(|i| forced(i)).count();
+            for i in &idxs[..start] {
+                set_offs[*i] = on[*i].amount();
+                cleared -= on[*i].amount();
+            }
+            while start < idxs.len() && cleared > O::Amount::zero() {
+                let priority = on[idxs[start]].priority().unwrap_or_default();
+                let end = idxs[start..]
+                    .iter()
+                    .position(|i| on[*i].priority().unwrap_or_default() != priority)
+                    .map_or(idxs.len(), |len| start + len);
+                let class = &idxs[start..end];
+                let total: O::Amount = class.iter().map(|i| on[*i].amount()).sum();
+
+                if cleared >= total {
+                    class.iter().for_each(|i| set_offs[*i] = on[*i].amount());
+                    cleared -= total;
+                } else {
+                    let obligations: Vec<_> = class.iter().map(|i| &on[*i]).collect();
+                    let allocated = self.policy.allocate(cleared, &obligations)?;
+                    let valid = allocated.len() == class.len()
+                        && allocated.iter().copied().sum::<O::Amount>() == cleared
+                        && allocated
+                            .iter()
+                            .zip(&obligations)
+                            .all(|(a, o)| *a >= O::Amount::zero() && *a <= o.amount());
+                    if !valid {
+                        return Err(Error::InvalidAllocation);
+                    }
+                    class
+                        .iter()
+                        .zip(allocated)
+                        .for_each(|(i, a)| set_offs[*i] = a);
+                    cleared = O::Amount::zero();
+                }
+                start = end;
+            }
+
+            // the bilaterally netted amount is attributed to the obligations in the same order
+            for i in idxs.iter() {
+                bilateral[*i] = min(netted, set_offs[*i]);
+                netted -= bilateral[*i];
+            }
+        }
+
+        let cycles = split_cycles(&on, &pairs, &set_offs, cycles);
+        let lengths = cycles
+            .iter()
+            .fold(BTreeMap::<_, usize>::new(), |mut acc, cycle| {
+                *acc.entry(cycle.firms.len()).or_default() += 1;
+                acc
+            });
+        for (len, count) in lengths {
+            log::info!("Cycles of length {len} = {count}");
+        }
+
+        let setoffs: Vec<_> = on
+            .iter()
+            .zip(set_offs)
+            .zip(reasons)
+            .map(|((o, set_off), reason)| {
+                SO::new(
+                    o.id(),
+                    o.debtor().clone(),
+                    o.creditor().clone(),
+                    o.amount(),
+                    set_off,
+                    o.amount() - set_off,
+                )
+                .with_priority(o.priority())
+                .with_reason(reason)
+            })
+            .collect();
+
+        // every unit of debt is either cleared or remains
+        let total_remainder: O::Amount = setoffs.iter().map(|so| so.remainder()).sum();
+        if total_remainder != remained {
+            return Err(Error::RemainderMismatch {
+                expected: format!("{remained:?}"),
+                actual: format!("{total_remainder:?}"),
+            });
+        }
+
+        // the cash each firm pays into or receives from the liquidity pool is the difference
+        // between what it is cleared as a creditor and what it is cleared as a debtor
+        let cash_position = liabilities.iter().fold(
+            BTreeMap::<_, O::Amount>::new(),
+            |mut acc, ((debtor, creditor), cleared)| {
+                *acc.entry(creditor.clone()).or_default() += *cleared;
+                *acc.entry(debtor.clone()).or_default() -= *cleared;
+                acc
+            },
+        );
+
+        let payments = payments_from_net_positions(&cash_position);
+
+        let report = RunReport {
+            nid,
+            total_debt: td,
+            total_remainder: remained,
+            total_cleared: tc,
+            bilateral: bc,
+            multilateral: mc,
+            lost,
+            optimal,
+            firms: net_position.len(),
+            obligations: on.len(),
+            ineligible,
+            indivisible,
+            solver_iterations: self.solver_iterations,
+            timings: Timings {
+                preparation,
+                clearing,
+                allocation: stopwatch.lap(),
+            },
+        };
+        self.report = Some(report.clone());
+
+        Ok(Clearing {
+            setoffs,
+            payments,
+            bilateral,
+            cycles,
+            lost,
+            optimal,
+            report,
+        })
+    }
+
+    // Clears the obligations at the `included` indices of `on`, where the obligations that are
+    // `true` in `decided` have to be set off entirely and the ones that are `false` not at all.
+    // Returns `None` if that isn't possible.
+    fn clear_pass(
+        &mut self,
+        on: &[O],
+        included: &BTreeSet<usize>,
+        decided: &BTreeMap<usize, bool>,
+        has_cash: bool,
+        liquidity: &BTreeMap<O::AccountId, O::Amount>,
+    ) -> Result<Option<Cleared<O>>, Error> {
+        // the modes of the clearing, in the order in which they amend the pass (see `Pass`)
+        let passes: [&dyn Pass<O>; 6] = [
+            &Bilateral {
+                enabled: self.bilateral
+                    && !matches!(self.max_cycle_length, Some(max_len) if max_len < 2),
+            },
+            &Components { prune: !has_cash },
+            &MaxCycleLength {
+                max_len: self.max_cycle_length,
+            },
+            &Caps,
+            &Priorities,
+            &Liquidity { liquidity },
+        ];
+
+        let mut state = State::new(on, included, decided, self.caps.clone());
+        for pass in passes {
+            pass.prepare(&mut state)?;
+        }
+
+        // run the (min-cost) max-flow algo on every component and collect the cleared amounts
+        let components = mem::take(&mut state.components);
+        state.cleared = match self.clear_components(components, &state, &passes)? {
+            Some(cleared) => cleared,
+            None => return Ok(None),
+        };
+
+        for pass in passes {
+            pass.finish(&mut state)?;
+        }
+
+        let State {
+            required,
+            netted,
+            cleared,
+            cycles,
+            ..
+        } = state;
+        let cleared = Cleared {
+            decided: decided.clone(),
+            netted,
+            multilateral: cleared,
+            cycles,
+            cancelled: self.cancelled,
+        };
+
+        // obligations that aren't on any cycle can't be set off at all
+        if required
+            .iter()
+            .any(|(pair, amount)| cleared.pair(pair) < *amount)
+        {
+            return Ok(None);
+        }
+        Ok(Some(cleared))
+    }
+
+    // Solve the components one after the other and sum up the amounts cleared between every pair
+    // of firms. Returns `None` if the forced debts can't all be set off.
+    #[cfg(not(feature = "parallel"))]
+    fn clear_components(
+        &mut self,
+        components: Vec<Vec<usize>>,
+        state: &State<'_, O>,
+        passes: &[&dyn Pass<O>],
+    ) -> Result<Solved<O>, Error> {
+        let mut cleared = BTreeMap::new();
+        for component in components {
+            let pairs = Self::clear(&mut self.algo, state, passes, &component)?;
+            self.solver_iterations += self.algo.iterations();
+            self.cancelled |= self.algo.cancelled();
+            match pairs {
+                Some(pairs) => add(&mut cleared, pairs),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(cleared))
+    }
+
+    // Solve the components concurrently and sum up the amounts cleared between every pair of
+    // firms. Returns `None` if the forced debts can't all be set off.
+    #[cfg(feature = "parallel")]
+    fn clear_components(
+        &mut self,
+        components: Vec<Vec<usize>>,
+        state: &State<'_, O>,
+        passes: &[&dyn Pass<O>],
+    ) -> Result<Solved<O>, Error> {
+        // every component is solved by its own clone of the algo, and the results are merged in
+        // the order of the components so that they don't depend on how the work is scheduled
+        let results = components
+            .into_iter()
+            .map(|component| (self.algo.clone(), component))
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|(mut algo, component)| {
+                Self::clear(&mut algo, state, passes, &component)
+                    .map(|pairs| (pairs, algo.cancelled(), algo.iterations()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut cleared = Some(BTreeMap::new());
+        for (pairs, cancelled, iterations) in results {
+            self.cancelled |= cancelled;
+            self.solver_iterations += iterations;
+            cleared = cleared.zip(pairs).map(|(mut cleared, pairs)| {
+                add(&mut cleared, pairs);
+                cleared
+            });
+        }
+        Ok(cleared)
+    }
+
+    // Run the min-cost flow algo on the obligations of `component` (less the debts that were netted
+    // bilaterally) in the flow network that the `passes` amend, and return the amount cleared for
+    // every pair of firms. Returns `None` if the forced debts can't all be set off.
+    fn clear(
+        algo: &mut Algo,
+        state: &State<'_, O>,
+        passes: &[&dyn Pass<O>],
+        component: &[usize],
+    ) -> Result<Solved<O>, Error> {
+        let mut network = Network::new(&state.obligations(component), &state.netted);
+        for pass in passes {
+            pass.network(state, &mut network)?;
+        }
+
+        // Add source and sink flows based on values of "b" vector
+        let edges: Vec<_> = network
+            .net_position
+            .iter()
+            .filter_map(|(firm, balance)| {
+                let edge = match balance.cmp(&O::Amount::zero()) {
+                    Ordering::Less => (Node::Source, firm.into()),
+                    Ordering::Greater => (firm.into(), Node::Sink),
+                    Ordering::Equal => return None,
+                };
+                Some((edge, max(*balance, -*balance)))
+            })
+            .collect();
+        for (edge, capacity) in edges {
+            network
+                .graph
+                .entry(edge)
+                .or_insert_with(|| EdgeWeight {
+                    cost: O::Amount::zero(),
+                    capacity: O::Amount::zero(),
+                })
+                .capacity += capacity;
+        }
+
+        // run the (min-cost) max-flow algo, all debt has to remain somewhere unless some of it was
+        // forced to be set off
+        let supply: O::Amount = network
+            .graph
+            .iter()
+            .filter(|((from, _), _)| *from == Node::Source)
+            .map(|(_, edge)| edge.capacity)
+            .sum();
+        let (flow, paths) = algo
+            .min_cost_flow(&network.graph)
+            .map_err(|e| Error::AlgoSpecific(format!("{e:?}")))?;
+        if flow < supply {
+            return Ok(None);
+        }
+
+        // substract minimum cost maximum flow from the debts to get the clearing solution
+        let mut debts = network.debts;
+        paths.into_iter().for_each(|((n1, n2), amount)| {
+            log::trace!("{:?} --> {:?}", n1, n2);
+
+            if let Some(debt) = debts.get_mut(&(n1, n2)) {
+                *debt -= amount;
+            }
+        });
+
+        Ok(Some(debts))
+    }
+
+    /// Checks set-off notices together with the cash payments returned by `run_with_liquidity`.
+    pub fn check_with_payments(
+        &self,
+        setoffs: &[SO],
+        payments: &[Payment<O::AccountId, O::Amount>],
+    ) -> Result<(), Error>
+    where
+        SO: Clone,
+    {
+        // a cash payment from A to B has the same effect on the net positions as fully setting off
+        // a (virtual) obligation from B to A
+        let setoffs = setoffs
+            .iter()
+            .cloned()
+            .chain(payments.iter().map(|p| {
+                SO::new(
+                    None,
+                    p.payee.clone(),
+                    p.payer.clone(),
+                    p.amount,
+                    p.amount,
+                    O::Amount::zero(),
+                )
+            }))
+            .collect();
+
+        self.check(&setoffs)
+    }
+}
+
+impl<O, SO, Algo, Policy, Elig> Mtcs for DefaultMtcs<O, SO, Algo, Policy, Elig>
+where
+    O: Obligation + MaybeSync,
+    O::AccountId: Id + MaybeSend + MaybeSync,
+    O::Amount: Int + CheckedAdd + CheckedMul + MaybeSend + MaybeSync,
+    SO: SetOff<Amount = O::Amount, AccountId = O::AccountId>,
+    Algo: MinCostFlow<
+            GraphIter = FlowGraph<O::AccountId, O::Amount, O::Amount>,
+            EdgeCapacity = O::Amount,
+            EdgeCost = O::Amount,
+        > + Clone
+        + MaybeSend,
+    <Algo as MinCostFlow>::Paths: IntoIterator<Item = ((O::AccountId, O::AccountId), O::Amount)>,
+    Policy: AllocationPolicy<O>,
+    Elig: Eligibility<O>,
+{
+    type Obligation = O;
+    type SetOff = SO;
+    type Obligations = Vec<O>;
+    type SetOffs = Vec<SO>;
+    type Algo = Algo;
+    type Error = Error;
+
+    fn run(&mut self, on: Self::Obligations) -> Result<Self::SetOffs, Self::Error> {
+        self.run_with_liquidity(on, &BTreeMap::new())
+            .map(|clearing| clearing.setoffs)
+    }
+
+    fn check(&self, setoffs: &Self::SetOffs) -> Result<(), Self::Error> {
+        // ba - net balance positions of the obligation network
+        let ba = setoffs.iter().fold(BTreeMap::<_, _>::new(), |mut acc, so| {
+            *acc.entry(so.creditor()).or_default() += so.amount();
+            *acc.entry(so.debtor()).or_default() -= so.amount();
+            acc
+        });
+
+        // bl - net balance positions of the remaining acyclic network
+        let bl = setoffs.iter().fold(BTreeMap::<_, _>::new(), |mut acc, so| {
+            *acc.entry(so.creditor()).or_default() += so.remainder();
+            *acc.entry(so.debtor()).or_default() -= so.remainder();
+            acc
+        });
+
+        // bc - net balance positions of the cyclic network
+        let bc = setoffs.iter().fold(BTreeMap::<_, _>::new(), |mut acc, so| {
+            *acc.entry(so.creditor()).or_default() += so.set_off();
+            *acc.entry(so.debtor()).or_default() -= so.set_off();
+            acc
+        });
+
+        // SUM(+NID) == SUM(-NID) for all b-vectors
+        for (vector, b) in [("obligation", &ba), ("set-off", &bc), ("remainder", &bl)] {
+            let (positive, negative) = b.values().fold(
+                (O::Amount::zero(), O::Amount::zero()),
+                |(positive, negative), amount| match *amount > O::Amount::zero() {
+                    true => (positive + *amount, negative),
+                    false => (positive, negative - *amount),
+                },
+            );
+            if positive != negative {
+                return Err(Error::UnbalancedNetPositions {
+                    vector,
+                    positive: format!("{positive:?}"),
+                    negative: format!("{negative:?}"),
+                });
+            }
+        }
+
+        // ba == bl
+        for (firm, before) in &ba {
+            let after = bl.get(firm).copied().unwrap_or_default();
+            if *before != after {
+                return Err(Error::NetPositionMismatch {
+                    firm: format!("{firm:?}"),
+                    before: format!("{before:?}"),
+                    after: format!("{after:?}"),
+                });
+            }
+        }
+
+        // set-off consistency check
+        // (i.e. the sum of all set-off amounts where Alice is a debtor equals the sum of all set-off amounts where Alice is a creditor)
+        let debtors = setoffs
+            .iter()
+            .fold(BTreeMap::<_, O::Amount>::new(), |mut acc, so| {
+                *acc.entry(so.debtor()).or_default() += so.set_off();
+                acc
+            });
+        let creditors = setoffs
+            .iter()
+            .fold(BTreeMap::<_, O::Amount>::new(), |mut acc, so| {
+                *acc.entry(so.creditor()).or_default() += so.set_off();
+                acc
+            });
+        for firm in debtors.keys().chain(creditors.keys()) {
+            let as_debtor = debtors.get(firm).copied().unwrap_or_default();
+            let as_creditor = creditors.get(firm).copied().unwrap_or_default();
+            if as_debtor != as_creditor {
+                return Err(Error::InconsistentSetOff {
+                    firm: format!("{firm:?}"),
+                    as_debtor: format!("{as_debtor:?}"),
+                    as_creditor: format!("{as_creditor:?}"),
+                });
+            }
+        }
+
+        let ba_len = ba.len();
+        let nid_a: O::Amount = ba
+            .into_values()
+            .filter(|amount| amount > &O::Amount::zero())
+            .sum();
+        let nid_c: O::Amount = bc
+            .into_values()
+            .filter(|amount| amount > &O::Amount::zero())
+            .sum();
+        let nid_l: O::Amount = bl
+            .into_values()
+            .filter(|amount| amount > &O::Amount::zero())
+            .sum();
+
+        // NID before and after algo run must be the same
+        if nid_a != nid_l {
+            return Err(Error::NidMismatch {
+                before: format!("{nid_a:?}"),
+                after: format!("{nid_l:?}"),
+            });
+        }
+
+        let debt_before: O::Amount = setoffs.iter().map(|s| s.amount()).sum();
+        let debt_after: O::Amount = setoffs.iter().map(|s| s.remainder()).sum();
+        let compensated: O::Amount = setoffs.iter().map(|s| s.set_off()).sum();
+
+        log::debug!("num of companies: {ba_len}");
+        log::debug!("      NID before: {nid_a}");
+        log::debug!(" NID compensated: {nid_c}");
+        log::debug!("       NID after: {nid_l}");
+        log::debug!("     Debt before: {debt_before}");
+        log::debug!(" Debt after + Co: {}", debt_after + compensated);
+        log::debug!("         Cleared: {compensated}");
+        log::debug!("      Debt after: {debt_after}");
+        log::debug!("Debt before - Co: {}", debt_before - compensated);
+
+        Ok(())
+    }
+}
+
+// Calculate the net balance position of every firm
+fn net_positions<'a, O>(on: impl Iterator<Item = &'a O>) -> BTreeMap<O::AccountId, O::Amount>
+where
+    O: Obligation + 'a,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    on.fold(BTreeMap::new(), |mut acc, o| {
+        *acc.entry(o.creditor().clone()).or_default() += o.amount(); // credit increases the net balance
+        *acc.entry(o.debtor().clone()).or_default() -= o.amount(); // debit decreases the net balance
+        acc
+    })
+}
+
+// The amounts between pairs of firms that one clearing pass nets bilaterally and clears
+// multilaterally, the cycles they were cleared along (with a max cycle length), which indivisible
+// obligations were (`true`) or weren't (`false`) set off entirely and whether the algo was
+// cancelled (in this or an earlier pass)
+struct Cleared<O: Obligation> {
+    decided: BTreeMap<usize, bool>,
+    netted: BTreeMap<(O::AccountId, O::AccountId), O::Amount>,
+    multilateral: BTreeMap<(O::AccountId, O::AccountId), O::Amount>,
+    cycles: Vec<Cycle<O::AccountId, O::Amount>>,
+    cancelled: bool,
+}
+
+impl<O> Cleared<O>
+where
+    O: Obligation,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    fn total(&self) -> O::Amount {
+        self.netted
+            .values()
+            .chain(self.multilateral.values())
+            .copied()
+            .sum()
+    }
+
+    fn pair(&self, pair: &(O::AccountId, O::AccountId)) -> O::Amount {
+        [&self.netted, &self.multilateral]
+            .iter()
+            .filter_map(|amounts| amounts.get(pair))
+            .copied()
+            .sum()
+    }
+}
+
+// The amount cleared between every pair of firms by the min-cost flow, or `None` if the forced
+// debts can't all be set off
+type Solved<O> = Option<Pairs<<O as Obligation>::AccountId, <O as Obligation>::Amount>>;
+
+// Calculate the debt between every pair of firms, less the debts that were `netted` bilaterally
+fn reduced_debts<O>(
+    on: &[&O],
+    netted: &BTreeMap<(O::AccountId, O::AccountId), O::Amount>,
+) -> BTreeMap<(O::AccountId, O::AccountId), O::Amount>
+where
+    O: Obligation,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    let mut debts = on.iter().fold(BTreeMap::new(), |mut acc, o| {
+        *acc.entry((o.debtor().clone(), o.creditor().clone()))
+            .or_default() += o.amount();
+        acc
+    });
+    for (pair, amount) in netted {
+        if let Some(debt) = debts.get_mut(pair) {
+            *debt -= *amount;
+        }
+    }
+    debts.retain(|_, debt| *debt > O::Amount::zero());
+    debts
+}
+
+// Add the amounts between pairs of firms `pairs` to `acc`
+fn add<AccountId, Amount>(acc: &mut Pairs<AccountId, Amount>, pairs: Pairs<AccountId, Amount>)
+where
+    AccountId: Id,
+    Amount: Int,
+{
+    for (pair, amount) in pairs {
+        *acc.entry(pair).or_default() += amount;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::algo::mcmf::cost_scaling::CostScaling;
+    use crate::algo::mcmf::native_simplex::NativeSimplex;
+    use crate::algo::mcmf::primal_dual::PrimalDual;
+    use crate::allocation::{LargestFirst, ProRata};
+    use crate::obligation::SimpleObligation;
+    use crate::setoff::SimpleSetoff;
+    use crate::testing::Rng;
+
+    type Setoffs = Vec<SimpleSetoff<u64, i64>>;
+
+    fn obligations(on: &[(u64, u64, i64, i64)]) -> Vec<SimpleObligation<u64, i64>> {
+        on.iter()
+            .enumerate()
+            .map(|(id, (debtor, creditor, amount, priority))| {
+                SimpleObligation::new(Some(id), *debtor, *creditor, *amount)
+                    .and_then(|o| o.with_priority(Some(*priority)))
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    fn run_all(on: &[(u64, u64, i64, i64)]) -> [Result<Setoffs, Error>; 3] {
+        [
+            DefaultMtcs::new(PrimalDual::default()).run(obligations(on)),
+            DefaultMtcs::new(CostScaling::default()).run(obligations(on)),
+            DefaultMtcs::new(NativeSimplex::default()).run(obligations(on)),
+        ]
+    }
+
+    #[test]
+    fn every_unit_is_costed_at_its_own_priority() {
+        // the debt from 2 to 3 is mostly low priority, so 2 should rather clear its claim on 4
+        let on = [
+            (1, 2, 10, 0),
+            (2, 3, 1, 5),
+            (2, 3, 9, 0),
+            (3, 1, 10, 0),
+            (2, 4, 10, 3),
+            (4, 1, 10, 0),
+        ];
+        for setoffs in run_all(&on) {
+            let set_offs: Vec<_> = setoffs.unwrap().iter().map(|so| so.set_off).collect();
+            assert_eq!(set_offs, [10, 1, 0, 1, 9, 9]);
+        }
+    }
+
+    #[test]
+    fn the_allocation_policy_splits_the_cleared_amount_of_a_pair() {
+        // 6 of the 10 owed from 1 to 2 can be cleared
+        let on = obligations(&[(1, 2, 4, 0), (1, 2, 6, 0), (2, 1, 6, 0)]);
+        let set_offs =
+            |setoffs: Setoffs| -> Vec<_> { setoffs.iter().map(|so| so.set_off).collect() };
+
+        let mut mtcs = DefaultMtcs::with_allocation_policy(PrimalDual::default(), LargestFirst);
+        assert_eq!(set_offs(mtcs.run(on.clone()).unwrap()), [0, 6, 6]);
+        let mut mtcs = DefaultMtcs::with_allocation_policy(PrimalDual::default(), ProRata);
+        let setoffs = mtcs.run(on.clone()).unwrap();
+        mtcs.check(&setoffs).unwrap();
+        assert_eq!(set_offs(setoffs), [2, 4, 6]);
+
+        // the amounts of a custom policy have to add up to the cleared amount
+        let too_much = |cleared: i64, on: &[&SimpleObligation<u64, i64>]| vec![cleared; on.len()];
+        let mut mtcs = DefaultMtcs::with_allocation_policy(PrimalDual::default(), too_much);
+        let setoffs: Result<Setoffs, _> = mtcs.run(on);
+        assert!(matches!(setoffs, Err(Error::InvalidAllocation)));
+    }
+
+    #[test]
+    fn firms_pay_at_most_their_liquidity() {
+        // only the cycle of 4 can be set off without cash
+        let on = obligations(&[(1, 2, 10, 0), (2, 3, 10, 0), (3, 1, 4, 0), (3, 4, 6, 0)]);
+        let liquidity = BTreeMap::from([(1, 3), (3, 2)]);
+        let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default());
+        let res = mtcs.run_with_liquidity(on, &liquidity).unwrap();
+        mtcs.check_with_payments(&res.setoffs, &res.payments)
+            .unwrap();
+
+        let paid = res.payments.iter().fold(BTreeMap::new(), |mut acc, p| {
+            *acc.entry(p.payer).or_default() += p.amount;
+            acc
+        });
+        assert!(paid.iter().all(|(payer, paid)| *paid <= liquidity[payer]));
+        assert_eq!(paid.values().sum::<i64>(), 5);
+        // the cash of 1 discharges 3 along the chain from 1 to 4 and that of 3 another 2 of its debt
+        // to 4
+        assert_eq!(res.report.total_cleared, 4 * 3 + 3 * 3 + 2);
+    }
+
+    #[test]
+    fn components_are_solved_as_if_on_their_own() {
+        // pseudo-random networks over disjoint sets of firms, which are solved concurrently with
+        // the `parallel` feature
+        let mut rng = Rng::new(0x2b99_2ddf_a232_49d6);
+        for _ in 0..20 {
+            let networks: Vec<_> = (0..4)
+                .map(|k| {
+                    let on: Vec<_> = (0..12)
+                        .map(|_| {
+                            let (debtor, creditor) = (rng.below(5), rng.below(5));
+                            let (amount, priority) = (1 + rng.below(9), rng.below(3));
+                            (
+                                10 * k + debtor,
+                                10 * k + creditor,
+                                amount as i64,
+                                priority as i64,
+                            )
+                        })
+                        .filter(|(debtor, creditor, _, _)| debtor != creditor)
+                        .collect();
+                    obligations(&on)
+                })
+                .collect();
+
+            let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default());
+            let separately: Vec<_> = networks
+                .iter()
+                .flat_map(|on| mtcs.run(on.clone()).unwrap())
+                .map(|so| so.set_off)
+                .collect();
+            let together: Vec<_> = mtcs
+                .run(networks.concat())
+                .unwrap()
+                .into_iter()
+                .map(|so| so.set_off)
+                .collect();
+            assert_eq!(together, separately);
+        }
+    }
+
+    #[test]
+    fn reciprocal_debts_are_netted_bilaterally_first() {
+        let on = obligations(&[(1, 2, 10, 0), (2, 1, 4, 0), (2, 3, 5, 0), (3, 1, 5, 0)]);
+        let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default())
+            .with_bilateral_netting();
+        let res = mtcs.run_with_liquidity(on, &BTreeMap::new()).unwrap();
+        mtcs.check(&res.setoffs).unwrap();
+
+        let set_offs: Vec<_> = res.setoffs.iter().map(|so| so.set_off).collect();
+        assert_eq!(set_offs, [9, 4, 5, 5]);
+        assert_eq!(res.bilateral, [4, 4, 0, 0]);
+        assert_eq!((res.report.bilateral, res.report.multilateral), (8, 15));
+    }
+
+    #[test]
+    fn set_offs_are_built_from_short_cycles() {
+        let mut rng = Rng::new(0x1405_7b7e_f767_814f);
+        for _ in 0..50 {
+            let on: Vec<_> = (0..10)
+                .map(|_| (rng.below(6), rng.below(6), 1 + rng.below(9) as i64, 0))
+                .filter(|(debtor, creditor, _, _)| debtor != creditor)
+                .collect();
+            let on = obligations(&on);
+            let unlimited = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default())
+                .run_with_liquidity(on.clone(), &BTreeMap::new())
+                .unwrap()
+                .report
+                .total_cleared;
+
+            for max_len in 2..=6 {
+                let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default())
+                    .with_max_cycle_length(max_len);
+                let res = mtcs
+                    .run_with_liquidity(on.clone(), &BTreeMap::new())
+                    .unwrap();
+                mtcs.check(&res.setoffs).unwrap();
+                assert!(res.cycles.iter().all(|cycle| cycle.firms.len() <= max_len));
+                for so in &res.setoffs {
+                    let covered: i64 = res
+                        .cycles
+                        .iter()
+                        .filter(|cycle| cycle.obligations.contains(&so.id))
+                        .map(|cycle| cycle.amount)
+                        .sum();
+                    assert_eq!(covered, so.set_off);
+                }
+                // every cycle is short enough if there are no more firms than that
+                if max_len == 6 {
+                    assert_eq!(res.report.total_cleared, unlimited);
+                }
+            }
+        }
+    }
+
+    // The total set-off of every firm as a debtor and as a creditor
+    fn set_off_by_firm(setoffs: &[SimpleSetoff<u64, i64>]) -> BTreeMap<u64, (i64, i64)> {
+        setoffs.iter().fold(BTreeMap::new(), |mut acc, so| {
+            acc.entry(so.debtor).or_insert((0, 0)).0 += so.set_off;
+            acc.entry(so.creditor).or_insert((0, 0)).1 += so.set_off;
+            acc
+        })
+    }
+
+    #[test]
+    fn capped_firms_set_off_at_most_their_cap() {
+        let mut rng = Rng::new(0x6a09_e667_f3bc_c909);
+        for _ in 0..50 {
+            let on: Vec<_> = (0..10)
+                .map(|_| (rng.below(5), rng.below(5), 1 + rng.below(9) as i64, 0))
+                .filter(|(debtor, creditor, _, _)| debtor != creditor)
+                .collect();
+            let on = obligations(&on);
+            let caps = BTreeMap::from([(0, rng.below(10) as i64), (1, rng.below(10) as i64)]);
+
+            for bilateral in [false, true] {
+                let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default())
+                    .with_caps(caps.clone())
+                    .with_excluded(BTreeSet::from([4]));
+                if bilateral {
+                    mtcs = mtcs.with_bilateral_netting();
+                }
+                let setoffs = mtcs.run(on.clone()).unwrap();
+                mtcs.check(&setoffs).unwrap();
+                let set_off = set_off_by_firm(&setoffs);
+                for (firm, cap) in &caps {
+                    let (as_debtor, as_creditor) = set_off.get(firm).copied().unwrap_or_default();
+                    assert!(as_debtor <= *cap && as_creditor <= *cap);
+                }
+                assert_eq!(set_off.get(&4).copied().unwrap_or_default(), (0, 0));
+            }
+        }
+    }
+
+    #[test]
+    fn caps_only_bind_below_what_a_firm_can_clear() {
+        // 2 can clear 6 along the cycle, of which its cap of 4 lets it clear 4
+        let on = obligations(&[(1, 2, 6, 0), (2, 3, 6, 0), (3, 1, 6, 0)]);
+        for (cap, cleared) in [(4, 4), (6, 6), (10, 6)] {
+            let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default())
+                .with_caps(BTreeMap::from([(2, cap)]));
+            let setoffs = mtcs.run(on.clone()).unwrap();
+            assert!(setoffs.iter().all(|so| so.set_off == cleared));
+        }
+    }
+
+    #[test]
+    fn ineligible_obligations_are_not_set_off() {
+        // without the disputed obligation from 3 to 1, only the cycle of 1 and 2 is left
+        let on = obligations(&[(1, 2, 5, 0), (2, 3, 5, 0), (3, 1, 5, 0), (2, 1, 2, 0)]);
+        let disputed =
+            |o: &SimpleObligation<u64, i64>| (o.id() == Some(2)).then(|| String::from("disputed"));
+        let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default())
+            .with_eligibility(disputed);
+        let res = mtcs.run_with_liquidity(on, &BTreeMap::new()).unwrap();
+        mtcs.check(&res.setoffs).unwrap();
+
+        let set_offs: Vec<_> = res.setoffs.iter().map(|so| so.set_off).collect();
+        assert_eq!(set_offs, [2, 0, 0, 2]);
+        let reasons: Vec<_> = res.setoffs.iter().map(|so| so.reason()).collect();
+        assert_eq!(reasons, [None, None, Some("disputed"), None]);
+        assert_eq!(res.report.ineligible, 1);
+    }
+
+    #[test]
+    fn check_reports_the_firm_of_a_tampered_set_off() {
+        let on = obligations(&[(1, 2, 10, 0), (2, 3, 10, 0), (3, 1, 5, 0)]);
+        let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default());
+        let setoffs = mtcs.run(on).unwrap();
+        assert!(mtcs.check(&setoffs).is_ok());
+
+        // 2 sets off less of its debt than of its claims
+        let mut tampered = setoffs;
+        tampered[1].set_off -= 1;
+        tampered[1].remainder += 1;
+        match mtcs.check(&tampered) {
+            Err(Error::NetPositionMismatch {
+                firm,
+                before,
+                after,
+            }) => assert_eq!((&*firm, &*before, &*after), ("2", "0", "-1")),
+            res => panic!("unexpected check result {res:?}"),
+        }
+    }
+
+    #[test]
+    fn caps_limit_what_is_discharged_by_cash() {
+        // the cash of 1 can discharge the chain from 1 to 3, but 2 only clears 4 of it
+        let on = obligations(&[(1, 2, 10, 0), (2, 3, 10, 0)]);
+        let liquidity = BTreeMap::from([(1, 10)]);
+        let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default())
+            .with_caps(BTreeMap::from([(2, 4)]));
+        let res = mtcs.run_with_liquidity(on, &liquidity).unwrap();
+        mtcs.check_with_payments(&res.setoffs, &res.payments)
+            .unwrap();
+        let set_offs: Vec<_> = res.setoffs.iter().map(|so| so.set_off).collect();
+        assert_eq!(set_offs, [4, 4]);
+        assert_eq!(
+            res.payments,
+            [Payment {
+                payer: 1,
+                payee: 3,
+                amount: 4
+            }]
+        );
+
+        let mut rng = Rng::new(0xbb67_ae85_84ca_a73b);
+        for _ in 0..50 {
+            let on: Vec<_> = (0..10)
+                .map(|_| (rng.below(5), rng.below(5), 1 + rng.below(9) as i64, 0))
+                .filter(|(debtor, creditor, _, _)| debtor != creditor)
+                .collect();
+            let on = obligations(&on);
+            let liquidity = BTreeMap::from([(0, rng.below(10) as i64), (2, rng.below(10) as i64)]);
+            let caps = BTreeMap::from([(0, rng.below(10) as i64), (1, rng.below(10) as i64)]);
+
+            let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default())
+                .with_caps(caps.clone());
+            let res = mtcs.run_with_liquidity(on.clone(), &liquidity).unwrap();
+            mtcs.check_with_payments(&res.setoffs, &res.payments)
+                .unwrap();
+            let set_off = set_off_by_firm(&res.setoffs);
+            for (firm, cap) in &caps {
+                let (as_debtor, as_creditor) = set_off.get(firm).copied().unwrap_or_default();
+                assert!(as_debtor <= *cap && as_creditor <= *cap);
+            }
+
+            // caps that are at least the total debts and claims don't change anything
+            let uncapped = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default())
+                .run_with_liquidity(on.clone(), &liquidity)
+                .unwrap();
+            let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default())
+                .with_caps(BTreeMap::from([(0, 100), (1, 100)]));
+            let res = mtcs.run_with_liquidity(on, &liquidity).unwrap();
+            assert_eq!(res.report.total_cleared, uncapped.report.total_cleared);
+        }
+    }
+
+    #[test]
+    fn caps_priorities_and_liquidity_are_combined() {
+        // 2 can only clear 5 of its debts, which it clears towards 3 (with the higher priority),
+        // so the cash of 4 (rather than that of 3) discharges its debt to 1
+        let on = obligations(&[
+            (1, 2, 10, 0),
+            (2, 3, 5, 5),
+            (2, 4, 5, 0),
+            (3, 1, 5, 0),
+            (4, 1, 5, 0),
+        ]);
+        let liquidity = BTreeMap::from([(3, 5), (4, 5)]);
+        let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default())
+            .with_caps(BTreeMap::from([(2, 5)]));
+        let res = mtcs.run_with_liquidity(on, &liquidity).unwrap();
+        mtcs.check_with_payments(&res.setoffs, &res.payments)
+            .unwrap();
+
+        let set_offs: Vec<_> = res.setoffs.iter().map(|so| so.set_off).collect();
+        assert_eq!(set_offs, [5, 5, 0, 5, 5]);
+        assert_eq!(
+            res.payments,
+            [Payment {
+                payer: 4,
+                payee: 1,
+                amount: 5
+            }]
+        );
+    }
+
+    // An obligation whose priority isn't validated (unlike that of `SimpleObligation`)
+    struct Unvalidated(u64, u64, i64, i64);
+
+    impl Obligation for Unvalidated {
+        type AccountId = u64;
+        type Amount = i64;
+
+        fn id(&self) -> Option<usize> {
+            None
+        }
+        fn debtor(&self) -> &u64 {
+            &self.0
+        }
+        fn creditor(&self) -> &u64 {
+            &self.1
+        }
+        fn amount(&self) -> i64 {
+            self.2
+        }
+        fn priority(&self) -> Option<i64> {
+            Some(self.3)
+        }
+    }
+
+    #[test]
+    fn negative_priorities_are_an_error() {
+        let on = vec![Unvalidated(1, 2, 10, 0), Unvalidated(2, 1, 10, -1)];
+        let setoffs: Result<Setoffs, _> = DefaultMtcs::new(PrimalDual::default()).run(on);
+        assert!(matches!(setoffs, Err(Error::NegativePriority)));
+    }
+
+    #[test]
+    fn cost_overflow_is_an_error() {
+        let on = [(1, 2, 10, i64::MAX / 4), (2, 1, 10, 0)];
+        for setoffs in run_all(&on) {
+            assert!(matches!(setoffs, Err(Error::CostOverflow)));
+        }
+    }
+
+    #[test]
+    fn report_counts_the_solver_iterations() {
+        let on = obligations(&[(1, 2, 10, 0), (2, 3, 10, 0), (3, 1, 5, 0)]);
+        let liquidity = BTreeMap::new();
+        let reports = [
+            DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default())
+                .run_with_liquidity(on.clone(), &liquidity)
+                .map(|res| res.report),
+            DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(CostScaling::default())
+                .run_with_liquidity(on.clone(), &liquidity)
+                .map(|res| res.report),
+            DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(NativeSimplex::default())
+                .run_with_liquidity(on, &liquidity)
+                .map(|res| res.report),
+        ];
+        for report in reports {
+            assert!(report.unwrap().solver_iterations > 0);
+        }
+    }
+
+    #[test]
+    fn the_report_of_the_last_run_is_kept() {
+        let on = obligations(&[(1, 2, 10, 0), (2, 3, 10, 0), (3, 1, 5, 0)]);
+        let report = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default())
+            .run_with_liquidity(on.clone(), &BTreeMap::new())
+            .unwrap()
+            .report;
+        assert_eq!(report.total_cleared, 15);
+
+        let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(PrimalDual::default());
+        assert_eq!(mtcs.last_report(), None);
+        mtcs.run(on).unwrap();
+        assert_eq!(mtcs.last_report(), Some(&report));
+    }
+
+    #[test]
+    fn cancelled_runs_are_feasible_but_not_optimal() {
+        let on = obligations(&[
+            (1, 2, 10, 0),
+            (2, 3, 10, 0),
+            (3, 1, 5, 0),
+            (2, 4, 5, 1),
+            (4, 1, 5, 0),
+        ]);
+        let liquidity = BTreeMap::new();
+        let cancel = |_: usize, _: i64| false;
+
+        let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(
+            PrimalDual::default().with_progress(cancel),
+        );
+        let res = mtcs.run_with_liquidity(on.clone(), &liquidity).unwrap();
+        assert!(!res.optimal);
+        mtcs.check_with_payments(&res.setoffs, &res.payments)
+            .unwrap();
+
+        let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(
+            CostScaling::default().with_progress(cancel),
+        );
+        let res = mtcs.run_with_liquidity(on.clone(), &liquidity).unwrap();
+        assert!(!res.optimal);
+        mtcs.check_with_payments(&res.setoffs, &res.payments)
+            .unwrap();
+
+        let mut mtcs = DefaultMtcs::<_, SimpleSetoff<_, _>, _>::new(
+            NativeSimplex::default().with_progress(cancel),
+        );
+        let res = mtcs.run_with_liquidity(on, &liquidity).unwrap();
+        assert!(!res.optimal);
+        mtcs.check_with_payments(&res.setoffs, &res.payments)
+            .unwrap();
+    }
+}
@@ -0,0 +1,198 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::algo::mcmf::FlowGraph;
+use crate::cycle::Cycle;
+use crate::error::Error;
+use crate::id::Id;
+use crate::int::Int;
+use crate::node::Node;
+use crate::obligation::Obligation;
+use crate::parallel::MaybeSync;
+
+use super::priority::Classes;
+use super::reduced_debts;
+
+// The amount between every pair of firms
+pub(super) type Pairs<AccountId, Amount> = BTreeMap<(AccountId, AccountId), Amount>;
+
+// A mode of the clearing (e.g. the bilateral pre-pass or the liquidity pool) that amends every
+// clearing pass, i.e. before its components are solved, the flow network of every component and
+// the amounts cleared after they are solved. Every hook does nothing by default.
+//
+// The passes run in a fixed order, which every pass relies on:
+// 1. `Bilateral` comes before `Components`, which only splits what is left after netting (and the
+//    netting uses up some of the caps).
+// 2. `Components` comes before `MaxCycleLength`, which picks the components that are too large for
+//    the min-cost flow.
+// 3. `Caps` comes before `Priorities`, so that the debts between the firms start and end at the
+//    nodes the capped firms are split into (see `Network::tail` and `Network::head`).
+// 4. `Liquidity` comes last and connects the pool to the firm nodes of the finished network.
+pub(super) trait Pass<O: Obligation>: MaybeSync {
+    // Amends the pass before its components are solved
+    fn prepare(&self, _state: &mut State<'_, O>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    // Amends the flow network of a component
+    fn network(
+        &self,
+        _state: &State<'_, O>,
+        _network: &mut Network<O::AccountId, O::Amount>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    // Amends the pass after its components are solved
+    fn finish(&self, _state: &mut State<'_, O>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+// A clearing pass over the obligations at the `included` indices of `on`, where the obligations
+// that are `true` in `decided` have to be set off entirely and the ones that are `false` not at all
+pub(super) struct State<'a, O: Obligation> {
+    pub(super) on: &'a [O],
+    // (less the obligations that aren't set off at all)
+    pub(super) included: Vec<usize>,
+    pub(super) decided: &'a BTreeMap<usize, bool>,
+    // the debt between every pair of firms
+    pub(super) debts: Pairs<O::AccountId, O::Amount>,
+    // the debt between every pair of firms that has to be set off
+    pub(super) required: Pairs<O::AccountId, O::Amount>,
+    // what is left of the caps of the firms
+    pub(super) caps: BTreeMap<O::AccountId, O::Amount>,
+    // the debt between every pair of firms that was netted bilaterally
+    pub(super) netted: Pairs<O::AccountId, O::Amount>,
+    pub(super) classes: Classes<O::AccountId, O::Amount>,
+    // the components that are solved by the min-cost flow (by obligation index)
+    pub(super) components: Vec<Vec<usize>>,
+    // the components that are cleared greedily (by obligation index)
+    pub(super) greedy: Vec<Vec<usize>>,
+    // the amount cleared multilaterally between every pair of firms
+    pub(super) cleared: Pairs<O::AccountId, O::Amount>,
+    pub(super) cycles: Vec<Cycle<O::AccountId, O::Amount>>,
+}
+
+impl<'a, O> State<'a, O>
+where
+    O: Obligation,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    pub(super) fn new(
+        on: &'a [O],
+        included: &BTreeSet<usize>,
+        decided: &'a BTreeMap<usize, bool>,
+        caps: BTreeMap<O::AccountId, O::Amount>,
+    ) -> Self {
+        let included: Vec<_> = included
+            .iter()
+            .copied()
+            .filter(|i| decided.get(i) != Some(&false))
+            .collect();
+        let debts = pairs(included.iter().map(|i| &on[*i]));
+        let required = pairs(
+            decided
+                .iter()
+                .filter(|(_, set_off)| **set_off)
+                .map(|(i, _)| &on[*i]),
+        );
+
+        Self {
+            on,
+            included,
+            decided,
+            debts,
+            required,
+            caps,
+            netted: BTreeMap::new(),
+            classes: Classes::new(),
+            components: vec![],
+            greedy: vec![],
+            cleared: BTreeMap::new(),
+            cycles: vec![],
+        }
+    }
+
+    // The obligations at the indices of `component`
+    pub(super) fn obligations(&self, component: &[usize]) -> Vec<&'a O> {
+        component.iter().map(|i| &self.on[*i]).collect()
+    }
+}
+
+// The flow network of a component
+pub(super) struct Network<AccountId, Amount> {
+    // the debt between every pair of firms, less what was netted bilaterally
+    pub(super) debts: Pairs<AccountId, Amount>,
+    // the b vector of the reduced network
+    pub(super) net_position: BTreeMap<AccountId, Amount>,
+    // the total debt of the obligations of the component
+    pub(super) total_debt: Amount,
+    // the firms whose debts start at their `Outgoing` node and whose claims end at their
+    // `Incoming` node
+    pub(super) split: BTreeSet<AccountId>,
+    pub(super) graph: FlowGraph<AccountId, Amount, Amount>,
+}
+
+impl<AccountId, Amount> Network<AccountId, Amount>
+where
+    AccountId: Id,
+    Amount: Int,
+{
+    pub(super) fn new<O>(on: &[&O], netted: &Pairs<AccountId, Amount>) -> Self
+    where
+        O: Obligation<AccountId = AccountId, Amount = Amount>,
+    {
+        let debts = reduced_debts(on, netted);
+
+        // calculate the b vector of the reduced network
+        let net_position = debts.iter().fold(
+            BTreeMap::<_, Amount>::new(),
+            |mut acc, ((debtor, creditor), debt)| {
+                *acc.entry(creditor.clone()).or_default() += *debt; // credit increases the net balance
+                *acc.entry(debtor.clone()).or_default() -= *debt; // debit decreases the net balance
+                acc
+            },
+        );
+
+        Self {
+            debts,
+            net_position,
+            total_debt: on.iter().map(|o| o.amount()).sum(),
+            split: BTreeSet::new(),
+            graph: FlowGraph::new(),
+        }
+    }
+
+    // The node that the debts of `firm` start from
+    pub(super) fn tail(&self, firm: &AccountId) -> Node<AccountId> {
+        match self.split.contains(firm) {
+            true => Node::Outgoing(firm.clone()),
+            false => Node::WithId(firm.clone()),
+        }
+    }
+
+    // The node that the claims of `firm` end at
+    pub(super) fn head(&self, firm: &AccountId) -> Node<AccountId> {
+        match self.split.contains(firm) {
+            true => Node::Incoming(firm.clone()),
+            false => Node::WithId(firm.clone()),
+        }
+    }
+}
+
+// Sum up the amounts of the obligations `on` between every pair of firms
+fn pairs<'a, O>(on: impl Iterator<Item = &'a O>) -> Pairs<O::AccountId, O::Amount>
+where
+    O: Obligation + 'a,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    on.fold(BTreeMap::new(), |mut acc, o| {
+        *acc.entry((o.debtor().clone(), o.creditor().clone()))
+            .or_default() += o.amount();
+        acc
+    })
+}
@@ -0,0 +1,134 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+
+use num_traits::{CheckedAdd, CheckedMul, One, Zero};
+
+use crate::algo::mcmf::EdgeWeight;
+use crate::error::Error;
+use crate::id::Id;
+use crate::int::Int;
+use crate::node::Node;
+use crate::obligation::Obligation;
+use crate::parallel::MaybeSync;
+
+use super::pass::{Network, Pairs, Pass, State};
+
+// The capacity for the remaining debt of every priority class of every pair of firms (by priority)
+pub(super) type Classes<AccountId, Amount> =
+    BTreeMap<(AccountId, AccountId), BTreeMap<Amount, Amount>>;
+
+// Costs the debt between the firms, so that maximising the set-off comes first and higher priority
+// obligations are set off before lower priority ones
+pub(super) struct Priorities;
+
+impl<O> Pass<O> for Priorities
+where
+    O: Obligation + MaybeSync,
+    O::AccountId: Id,
+    O::Amount: Int + CheckedAdd + CheckedMul,
+{
+    fn prepare(&self, state: &mut State<'_, O>) -> Result<(), Error> {
+        state.classes = priority_classes(
+            state.on,
+            &state.included,
+            state.decided,
+            &state.netted,
+            &state.required,
+        );
+        Ok(())
+    }
+
+    fn network(
+        &self,
+        state: &State<'_, O>,
+        network: &mut Network<O::AccountId, O::Amount>,
+    ) -> Result<(), Error> {
+        // the priority classes of the debt between every pair of firms
+        let classes: Vec<_> = network
+            .debts
+            .keys()
+            .filter_map(|pair| Some((pair, state.classes.get(pair)?)))
+            .collect();
+
+        // every unit of flow over an obligation remains as debt, so it is assigned a base cost
+        // that outweighs any difference in priorities (i.e. maximising the set-off always comes
+        // first) plus its priority (i.e. higher priority obligations are set off first)
+        let max_priority = classes
+            .iter()
+            .flat_map(|(_, classes)| classes.keys())
+            .copied()
+            .max()
+            .unwrap_or_default();
+        let base_cost = max_priority
+            .checked_mul(&network.total_debt)
+            .and_then(|cost| cost.checked_add(&O::Amount::one()))
+            .ok_or(Error::CostOverflow)?;
+
+        // The flow carries the remaining debt of every priority class (less the debts that have to
+        // be set off) over an edge of its own, so that every unit is costed at its own priority.
+        // The lowest class of a pair is an edge between the firms, the others are routed through a
+        // node of their own.
+        let mut edges = vec![];
+        for ((debtor, creditor), classes) in classes {
+            let classes = classes
+                .iter()
+                .filter(|(_, capacity)| **capacity > O::Amount::zero());
+            for (rank, (priority, capacity)) in classes.enumerate() {
+                let weight = |cost| EdgeWeight {
+                    cost,
+                    capacity: *capacity,
+                };
+                let cost = base_cost.checked_add(priority).ok_or(Error::CostOverflow)?;
+                if rank == 0 {
+                    edges.push(((network.tail(debtor), network.head(creditor)), weight(cost)));
+                } else {
+                    let class = Node::PriorityClass(debtor.clone(), creditor.clone(), rank);
+                    edges.push(((network.tail(debtor), class.clone()), weight(cost)));
+                    edges.push(((class, network.head(creditor)), weight(O::Amount::zero())));
+                }
+            }
+        }
+        network.graph.extend(edges);
+        Ok(())
+    }
+}
+
+// Calculate the capacity for the remaining debt of every priority class of every pair of firms. The
+// obligations that have to be set off entirely (`true` in `decided`) can't leave any remainder, and
+// what was netted beyond them is set off from the highest priorities first (as it is allocated).
+fn priority_classes<O>(
+    on: &[O],
+    included: &[usize],
+    decided: &BTreeMap<usize, bool>,
+    netted: &Pairs<O::AccountId, O::Amount>,
+    required: &Pairs<O::AccountId, O::Amount>,
+) -> Classes<O::AccountId, O::Amount>
+where
+    O: Obligation,
+    O::AccountId: Id,
+    O::Amount: Int,
+{
+    let mut classes = included
+        .iter()
+        .filter(|i| !decided.contains_key(i))
+        .map(|i| &on[*i])
+        .fold(Classes::new(), |mut acc, o| {
+            *acc.entry((o.debtor().clone(), o.creditor().clone()))
+                .or_default()
+                .entry(o.priority().unwrap_or_default())
+                .or_default() += o.amount();
+            acc
+        });
+    for (pair, classes) in &mut classes {
+        let netted = netted.get(pair).copied().unwrap_or_default();
+        let mut excess = netted - required.get(pair).copied().unwrap_or_default();
+        for capacity in classes.values_mut().rev() {
+            let amount = min(max(excess, O::Amount::zero()), *capacity);
+            *capacity -= amount;
+            excess -= amount;
+        }
+    }
+    classes
+}
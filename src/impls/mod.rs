@@ -1,3 +1,4 @@
 pub mod complex_id;
 pub mod complex_id_map;
 pub mod default;
+pub mod netting;
@@ -0,0 +1,255 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use num_traits::Zero;
+
+use crate::error::Error;
+use crate::id::Id;
+use crate::int::Int;
+use crate::obligation::Obligation;
+use crate::payment::{payments_from_net_positions, Payment};
+use crate::setoff::SetOff;
+use crate::Mtcs;
+
+/// The outcome of multilateral netting.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Netting<SO, AccountId, Amount> {
+    /// The set-off notices, all of which discharge their obligation in full
+    pub setoffs: Vec<SO>,
+    /// The net position of every firm, i.e. positive for firms that receive and negative for firms
+    /// that pay cash
+    pub net_positions: BTreeMap<AccountId, Amount>,
+    /// The payments that settle the net positions
+    pub payments: Vec<Payment<AccountId, Amount>>,
+}
+
+impl<SO, AccountId, Amount> IntoIterator for Netting<SO, AccountId, Amount> {
+    type Item = SO;
+    type IntoIter = alloc::vec::IntoIter<SO>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.setoffs.into_iter()
+    }
+}
+
+/// Classic multilateral netting, i.e. every obligation is discharged in full and every firm pays
+/// (or receives) exactly its net position.
+#[derive(Clone, Debug)]
+pub struct NettingMtcs<O, SO> {
+    _phantom: PhantomData<(O, SO)>,
+}
+
+impl<O, SO> NettingMtcs<O, SO> {
+    pub fn new() -> Self {
+        Self {
+            _phantom: Default::default(),
+        }
+    }
+}
+
+impl<O, SO> Default for NettingMtcs<O, SO> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O, SO> Mtcs for NettingMtcs<O, SO>
+where
+    O: Obligation,
+    O::AccountId: Id,
+    O::Amount: Int,
+    SO: SetOff<Amount = O::Amount, AccountId = O::AccountId>,
+{
+    type Obligation = O;
+    type SetOff = SO;
+    type Obligations = Vec<O>;
+    type SetOffs = Netting<SO, O::AccountId, O::Amount>;
+    type Algo = ();
+    type Error = Error;
+
+    fn run(&mut self, on: Self::Obligations) -> Result<Self::SetOffs, Self::Error> {
+        // calculate the b vector
+        let net_positions = on
+            .iter()
+            .fold(BTreeMap::<_, O::Amount>::new(), |mut acc, o| {
+                *acc.entry(o.creditor().clone()).or_default() += o.amount(); // credit increases the net balance
+                *acc.entry(o.debtor().clone()).or_default() -= o.amount(); // debit decreases the net balance
+                acc
+            });
+
+        let payments = payments_from_net_positions(&net_positions);
+        let setoffs = on
+            .iter()
+            .map(|o| {
+                SO::new(
+                    o.id(),
+                    o.debtor().clone(),
+                    o.creditor().clone(),
+                    o.amount(),
+                    o.amount(),
+                    O::Amount::zero(),
+                )
+                .with_priority(o.priority())
+            })
+            .collect();
+
+        log::info!("----------------------------------");
+        log::info!("    Firms = {}", net_positions.len());
+        log::info!(" Payments = {}", payments.len());
+
+        Ok(Netting {
+            setoffs,
+            net_positions,
+            payments,
+        })
+    }
+
+    fn check(&self, netting: &Self::SetOffs) -> Result<(), Self::Error> {
+        // every obligation is discharged in full, i.e. nothing of it remains
+        if netting
+            .setoffs
+            .iter()
+            .any(|so| so.set_off() != so.amount() || so.remainder() != O::Amount::zero())
+        {
+            let remainder: O::Amount = netting
+                .setoffs
+                .iter()
+                .map(|so| so.amount() - so.set_off())
+                .sum();
+            return Err(Error::RemainderMismatch {
+                expected: format!("{:?}", O::Amount::zero()),
+                actual: format!("{remainder:?}"),
+            });
+        }
+
+        // the net positions match the obligations
+        let ba = netting
+            .setoffs
+            .iter()
+            .fold(BTreeMap::<_, O::Amount>::new(), |mut acc, so| {
+                *acc.entry(so.creditor().clone()).or_default() += so.amount();
+                *acc.entry(so.debtor().clone()).or_default() -= so.amount();
+                acc
+            });
+        check_positions(&ba, &netting.net_positions)?;
+
+        // the payments settle the net positions
+        let bp = netting
+            .payments
+            .iter()
+            .fold(BTreeMap::<_, O::Amount>::new(), |mut acc, p| {
+                *acc.entry(p.payee.clone()).or_default() += p.amount;
+                *acc.entry(p.payer.clone()).or_default() -= p.amount;
+                acc
+            });
+        check_positions(&netting.net_positions, &bp)?;
+
+        let nid: O::Amount = netting
+            .net_positions
+            .values()
+            .copied()
+            .filter(|amount| *amount > O::Amount::zero())
+            .sum();
+        log::debug!("num of companies: {}", netting.net_positions.len());
+        log::debug!("             NID: {nid}");
+        log::debug!("        Payments: {}", netting.payments.len());
+
+        Ok(())
+    }
+}
+
+// Check that every firm has the same net position `before` and `after` (where a missing firm has a
+// zero net position)
+fn check_positions<AccountId, Amount>(
+    before: &BTreeMap<AccountId, Amount>,
+    after: &BTreeMap<AccountId, Amount>,
+) -> Result<(), Error>
+where
+    AccountId: Id,
+    Amount: Int,
+{
+    for firm in before.keys().chain(after.keys()) {
+        let before = before.get(firm).copied().unwrap_or_default();
+        let after = after.get(firm).copied().unwrap_or_default();
+        if before != after {
+            return Err(Error::NetPositionMismatch {
+                firm: format!("{firm:?}"),
+                before: format!("{before:?}"),
+                after: format!("{after:?}"),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obligation::SimpleObligation;
+    use crate::setoff::SimpleSetoff;
+
+    fn netting() -> Netting<SimpleSetoff<u64, i64>, u64, i64> {
+        let on = [(1, 2, 10), (2, 3, 4)]
+            .iter()
+            .map(|(debtor, creditor, amount)| {
+                SimpleObligation::new(None, *debtor, *creditor, *amount).unwrap()
+            })
+            .collect();
+        NettingMtcs::new().run(on).unwrap()
+    }
+
+    #[test]
+    fn every_firm_pays_its_net_position() {
+        let netting = netting();
+        assert!(netting
+            .setoffs
+            .iter()
+            .all(|so| so.set_off == so.amount && so.remainder == 0));
+        assert_eq!(
+            netting.net_positions,
+            BTreeMap::from([(1, -10), (2, 6), (3, 4)])
+        );
+        let mut payments: Vec<_> = netting
+            .payments
+            .iter()
+            .map(|p| (p.payer, p.payee, p.amount))
+            .collect();
+        payments.sort();
+        assert_eq!(payments, [(1, 2, 6), (1, 3, 4)]);
+    }
+
+    #[test]
+    fn check_accepts_the_netting() {
+        assert!(NettingMtcs::<SimpleObligation<u64, i64>, _>::new()
+            .check(&netting())
+            .is_ok());
+    }
+
+    #[test]
+    fn check_rejects_a_tampered_netting() {
+        let mtcs = NettingMtcs::<SimpleObligation<u64, i64>, _>::new();
+
+        let mut tampered = netting();
+        tampered.setoffs[0] = SimpleSetoff::new(None, 1, 2, 10, 6, 4);
+        assert!(matches!(
+            mtcs.check(&tampered),
+            Err(Error::RemainderMismatch { .. })
+        ));
+
+        let mut tampered = netting();
+        tampered.net_positions.remove(&1);
+        assert!(matches!(
+            mtcs.check(&tampered),
+            Err(Error::NetPositionMismatch { .. })
+        ));
+
+        let mut tampered = netting();
+        tampered.payments.clear();
+        assert!(matches!(
+            mtcs.check(&tampered),
+            Err(Error::NetPositionMismatch { .. })
+        ));
+    }
+}
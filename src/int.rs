@@ -1,6 +1,6 @@
 use core::fmt::{Debug, Display};
 use core::iter::Sum;
-use core::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use core::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
 
 use num_traits::{One, Zero};
 
@@ -10,6 +10,8 @@ pub trait Int:
     + Sum<Self>
     + Add<Output = Self>
     + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
     + Neg<Output = Self>
     + Ord
     + AddAssign
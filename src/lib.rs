@@ -3,7 +3,7 @@
 //! This crate provides -
 //! * A library containing implementations of algorithms used for MTCS.
 //! * A CLI tool that runs MTCS on a specified input CSV file (containing a list of obligations) and
-//! outputs the resulting set-off notices as a CSV file.
+//!   outputs the resulting set-off notices as a CSV file.
 //!
 //! This crate implements MTCS using the minimum-cost maximum-flow algorithms based on ideas from
 //! the following paper -
@@ -24,17 +24,27 @@
 extern crate alloc;
 
 pub mod algo;
+pub mod allocation;
+pub mod cycle;
+pub mod eligibility;
 pub mod error;
 pub mod id;
 pub mod impls;
 pub mod int;
 pub mod node;
 pub mod obligation;
+pub mod parallel;
+pub mod payment;
+pub mod report;
 pub mod setoff;
+#[cfg(test)]
+mod testing;
+pub mod verify;
 
 pub use impls::complex_id::ComplexIdMtcs;
 pub use impls::complex_id_map::ComplexIdMapMtcs;
 pub use impls::default::DefaultMtcs;
+pub use impls::netting::NettingMtcs;
 
 use crate::obligation::Obligation;
 use crate::setoff::SetOff;
@@ -5,7 +5,32 @@ use crate::id::Id;
 pub enum Node<N> {
     Source,
     Sink,
+    /// The pool through which cash contributed by the firms is paid out
+    Liquidity,
     WithId(N),
+    /// The outgoing side of a firm whose set-off is capped, i.e. the node that its obligations
+    /// start from (while its cash and net position stay at the firm's `WithId` node)
+    Outgoing(N),
+    /// The incoming side of a firm whose set-off is capped, i.e. the node that its claims end at
+    Incoming(N),
+    /// A priority class of the obligations from a debtor to a creditor (by its rank among the
+    /// classes of the pair), through which the debt of that class is routed so that it's costed at
+    /// its own priority
+    PriorityClass(N, N, usize),
+}
+
+impl<N> Node<N> {
+    /// The firm that this node represents (if any), where a priority class is on the side of the
+    /// debtor.
+    pub fn firm(&self) -> Option<&N> {
+        match self {
+            Node::WithId(id)
+            | Node::Outgoing(id)
+            | Node::Incoming(id)
+            | Node::PriorityClass(id, _, _) => Some(id),
+            _ => None,
+        }
+    }
 }
 
 impl<N> From<N> for Node<N>
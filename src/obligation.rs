@@ -12,6 +12,15 @@ pub trait Obligation {
     fn debtor(&self) -> &Self::AccountId;
     fn creditor(&self) -> &Self::AccountId;
     fn amount(&self) -> Self::Amount;
+    /// The priority-of-claims of this obligation, higher priorities are set off first. A negative
+    /// priority is rejected by `DefaultMtcs`.
+    fn priority(&self) -> Option<Self::Amount> {
+        None
+    }
+    /// Whether this obligation has to be set off entirely or not at all.
+    fn indivisible(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
@@ -25,6 +34,8 @@ pub struct SimpleObligation<AccountId, Amount> {
     debtor: AccountId,
     creditor: AccountId,
     amount: Amount,
+    priority: Option<Amount>,
+    indivisible: bool,
 }
 
 impl<AccountId, Amount> SimpleObligation<AccountId, Amount>
@@ -48,9 +59,26 @@ where
                 debtor,
                 creditor,
                 amount,
+                priority: None,
+                indivisible: false,
             })
         }
     }
+
+    pub fn with_priority(mut self, priority: Option<Amount>) -> Result<Self, Error> {
+        match priority {
+            Some(p) if p < Amount::zero() => Err(Error::NegativePriority),
+            _ => {
+                self.priority = priority;
+                Ok(self)
+            }
+        }
+    }
+
+    pub fn with_indivisible(mut self, indivisible: bool) -> Self {
+        self.indivisible = indivisible;
+        self
+    }
 }
 
 impl<AccountId, Amount> Obligation for SimpleObligation<AccountId, Amount>
@@ -75,6 +103,14 @@ where
     fn amount(&self) -> Self::Amount {
         self.amount
     }
+
+    fn priority(&self) -> Option<Self::Amount> {
+        self.priority
+    }
+
+    fn indivisible(&self) -> bool {
+        self.indivisible
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
@@ -83,6 +119,8 @@ pub struct RawObligation<AccountId, Amount> {
     pub debtor: AccountId,
     pub creditor: AccountId,
     pub amount: Amount,
+    pub priority: Option<Amount>,
+    pub indivisible: Option<bool>,
 }
 
 impl<AccountId, Amount> TryFrom<RawObligation<AccountId, Amount>>
@@ -94,7 +132,9 @@ where
     type Error = Error;
 
     fn try_from(o: RawObligation<AccountId, Amount>) -> Result<Self, Self::Error> {
-        Self::new(o.id, o.debtor, o.creditor, o.amount)
+        Ok(Self::new(o.id, o.debtor, o.creditor, o.amount)?
+            .with_priority(o.priority)?
+            .with_indivisible(o.indivisible.unwrap_or_default()))
     }
 }
 
@@ -107,6 +147,8 @@ impl<AccountId, Amount> From<SimpleObligation<AccountId, Amount>>
             debtor: o.debtor,
             creditor: o.creditor,
             amount: o.amount,
+            priority: o.priority,
+            indivisible: Some(o.indivisible),
         }
     }
 }
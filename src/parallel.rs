@@ -0,0 +1,30 @@
+//! Marker traits for the bounds that are only required with the `parallel` feature, i.e. when the
+//! independent components of an obligation network are solved concurrently.
+
+/// Same as `Send` with the `parallel` feature, implemented by every type otherwise.
+#[cfg(feature = "parallel")]
+pub trait MaybeSend: Send {}
+
+#[cfg(feature = "parallel")]
+impl<T: Send + ?Sized> MaybeSend for T {}
+
+/// Same as `Send` with the `parallel` feature, implemented by every type otherwise.
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSend {}
+
+#[cfg(not(feature = "parallel"))]
+impl<T: ?Sized> MaybeSend for T {}
+
+/// Same as `Sync` with the `parallel` feature, implemented by every type otherwise.
+#[cfg(feature = "parallel")]
+pub trait MaybeSync: Sync {}
+
+#[cfg(feature = "parallel")]
+impl<T: Sync + ?Sized> MaybeSync for T {}
+
+/// Same as `Sync` with the `parallel` feature, implemented by every type otherwise.
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSync {}
+
+#[cfg(not(feature = "parallel"))]
+impl<T: ?Sized> MaybeSync for T {}
@@ -0,0 +1,256 @@
+use alloc::collections::{BTreeMap, BinaryHeap};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{min, Reverse};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cycle::Cycle;
+use crate::id::Id;
+use crate::int::Int;
+use crate::report::RunReport;
+use crate::setoff::SetOff;
+
+/// A cash payment instruction.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Payment<AccountId, Amount> {
+    pub payer: AccountId,
+    pub payee: AccountId,
+    pub amount: Amount,
+}
+
+/// The outcome of a clearing round that involves cash, i.e. the set-off notices together with the
+/// cash payments that the firms have to make.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Clearing<SetOff, AccountId, Amount> {
+    pub setoffs: Vec<SetOff>,
+    pub payments: Vec<Payment<AccountId, Amount>>,
+    /// The part of the `set_off` of every set-off notice (in the same order) that was netted by
+    /// the bilateral pre-pass
+    pub bilateral: Vec<Amount>,
+    /// The cycles of obligations that the set-offs were built from if the cycle length was limited
+    /// (see `DefaultMtcs::with_max_cycle_length`), empty otherwise
+    pub cycles: Vec<Cycle<AccountId, Amount>>,
+    /// The amount that could be cleared in addition if the indivisible obligations could be set
+    /// off partially
+    pub lost: Amount,
+    /// Whether the set-off clears as much as possible, i.e. `false` if the min-cost flow algo was
+    /// cancelled and only returned the set-off found so far
+    pub optimal: bool,
+    /// The statistics of the clearing run
+    pub report: RunReport<Amount>,
+}
+
+/// The max number of firms with a non-zero net position for which the smallest set of payments is
+/// searched exhaustively.
+const MAX_EXACT_FIRMS: usize = 16;
+
+/// Turns net cash positions (i.e. positive for firms that receive and negative for firms that pay
+/// cash) into payment instructions. The positions must sum up to zero.
+///
+/// Settling `n` firms takes at most `n - 1` payments, and one less for every group of firms whose
+/// positions sum up to zero on their own. For up to `MAX_EXACT_FIRMS` firms the firms are split
+/// into as many such groups as possible, which yields the smallest number of payments. Within a
+/// group (or with more firms), payers and payees with exactly offsetting positions are matched
+/// first, then the largest remaining payer pays the largest remaining payee.
+pub fn payments_from_net_positions<AccountId, Amount>(
+    net_position: &BTreeMap<AccountId, Amount>,
+) -> Vec<Payment<AccountId, Amount>>
+where
+    AccountId: Id,
+    Amount: Int,
+{
+    let positions: Vec<_> = net_position
+        .iter()
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(firm, amount)| (firm, *amount))
+        .collect();
+    if positions.len() > MAX_EXACT_FIRMS {
+        return greedy_payments(positions);
+    }
+
+    zero_sum_groups(&positions)
+        .into_iter()
+        .flat_map(greedy_payments)
+        .collect()
+}
+
+// Split the positions into the max number of groups that sum up to zero. `groups[mask]` is the max
+// number of zero-sum groups the positions in `mask` can be split into, one of which may be
+// incomplete.
+fn zero_sum_groups<'a, AccountId, Amount: Int>(
+    positions: &[(&'a AccountId, Amount)],
+) -> Vec<Vec<(&'a AccountId, Amount)>> {
+    let full = (1usize << positions.len()) - 1;
+    let mut sum = vec![Amount::zero(); full + 1];
+    let mut groups = vec![0usize; full + 1];
+    for mask in 1..=full {
+        let lowest = mask.trailing_zeros() as usize;
+        sum[mask] = sum[mask & (mask - 1)] + positions[lowest].1;
+        let best = (0..positions.len())
+            .filter(|i| mask & (1 << i) != 0)
+            .map(|i| groups[mask & !(1 << i)])
+            .max()
+            .unwrap_or_default();
+        groups[mask] = best + usize::from(sum[mask].is_zero());
+    }
+
+    // remove positions one at a time while keeping the max number of groups, every time the
+    // remaining positions sum up to zero a group is complete
+    let mut result = vec![];
+    let mut group = vec![];
+    let mut mask = full;
+    while mask != 0 {
+        let closes = usize::from(sum[mask].is_zero());
+        let i = (0..positions.len())
+            .find(|i| mask & (1 << i) != 0 && groups[mask & !(1 << i)] + closes == groups[mask])
+            .unwrap_or_default();
+        group.push(positions[i]);
+        mask &= !(1 << i);
+        if sum[mask].is_zero() {
+            result.push(core::mem::take(&mut group));
+        }
+    }
+
+    result
+}
+
+// Match payers and payees with the same amount, then let the largest payer pay the largest payee
+fn greedy_payments<AccountId, Amount>(
+    positions: Vec<(&AccountId, Amount)>,
+) -> Vec<Payment<AccountId, Amount>>
+where
+    AccountId: Id,
+    Amount: Int,
+{
+    let mut payments = Vec::new();
+
+    // match payers and payees with the same amount
+    let mut payees = positions
+        .iter()
+        .filter(|(_, amount)| *amount > Amount::zero())
+        .fold(BTreeMap::<_, Vec<_>>::new(), |mut acc, (firm, amount)| {
+            acc.entry(*amount).or_default().push(*firm);
+            acc
+        });
+    let mut payers = BinaryHeap::new();
+    for (firm, amount) in positions {
+        if amount >= Amount::zero() {
+            continue;
+        }
+        match payees.get_mut(&-amount).and_then(Vec::pop) {
+            Some(payee) => payments.push(Payment {
+                payer: firm.clone(),
+                payee: payee.clone(),
+                amount: -amount,
+            }),
+            None => payers.push((-amount, Reverse(firm))),
+        }
+    }
+    let mut payees: BinaryHeap<_> = payees
+        .into_iter()
+        .flat_map(|(amount, firms)| firms.into_iter().map(move |firm| (amount, Reverse(firm))))
+        .collect();
+
+    // the largest payer pays the largest payee
+    while let (Some((paid, Reverse(payer))), Some((received, Reverse(payee)))) =
+        (payers.pop(), payees.pop())
+    {
+        let amount = min(paid, received);
+        payments.push(Payment {
+            payer: payer.clone(),
+            payee: payee.clone(),
+            amount,
+        });
+        if paid > amount {
+            payers.push((paid - amount, Reverse(payer)));
+        }
+        if received > amount {
+            payees.push((received - amount, Reverse(payee)));
+        }
+    }
+
+    payments
+}
+
+/// Returns the payments that settle the remainders of the set-off notices, i.e. after a set-off
+/// every firm pays (or receives) its net position in the remaining (acyclic) obligation network.
+///
+/// The total amount paid equals the net internal debt (NID) of the obligation network.
+pub fn settlement_plan<SO, AccountId, Amount>(setoffs: &[SO]) -> Vec<Payment<AccountId, Amount>>
+where
+    SO: SetOff<AccountId = AccountId, Amount = Amount>,
+    AccountId: Id,
+    Amount: Int,
+{
+    // bl - net balance positions of the remaining acyclic network
+    let bl = setoffs
+        .iter()
+        .fold(BTreeMap::<_, Amount>::new(), |mut acc, so| {
+            *acc.entry(so.creditor().clone()).or_default() += so.remainder();
+            *acc.entry(so.debtor().clone()).or_default() -= so.remainder();
+            acc
+        });
+
+    payments_from_net_positions(&bl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setoff::SimpleSetoff;
+    use crate::testing::Rng;
+
+    // The net position of every firm after the payments
+    fn settled(payments: &[Payment<u64, i64>]) -> BTreeMap<u64, i64> {
+        payments.iter().fold(BTreeMap::new(), |mut acc, p| {
+            assert!(p.amount > 0);
+            *acc.entry(p.payee).or_default() += p.amount;
+            *acc.entry(p.payer).or_default() -= p.amount;
+            acc
+        })
+    }
+
+    #[test]
+    fn payments_settle_the_net_positions() {
+        let mut rng = Rng::new(0x5851_f42d_4c95_7f2d);
+        for firms in [4, 12, 24] {
+            for _ in 0..50 {
+                let mut positions: BTreeMap<u64, i64> = (0..firms - 1)
+                    .map(|firm| (firm, rng.below(9) as i64 - 4))
+                    .collect();
+                let last = -positions.values().sum::<i64>();
+                positions.insert(firms - 1, last);
+
+                let payments = payments_from_net_positions(&positions);
+                positions.retain(|_, amount| *amount != 0);
+                assert_eq!(settled(&payments), positions);
+                assert!(payments.len() < positions.len().max(1));
+            }
+        }
+    }
+
+    #[test]
+    fn offsetting_groups_are_settled_on_their_own() {
+        // {1, 2} and {3, 4, 5} sum up to zero, which takes 1 + 2 payments instead of 4
+        let positions = BTreeMap::from([(1, -3), (2, 3), (3, -5), (4, 2), (5, 3)]);
+        let payments = payments_from_net_positions(&positions);
+        assert_eq!(settled(&payments), positions);
+        assert_eq!(payments.len(), 3);
+    }
+
+    #[test]
+    fn the_settlement_plan_pays_the_remainder_nid() {
+        // 5 of the cycle between 1, 2 and 3 is set off, 1 still owes 5 to 2 and 2 owes 3 to 4
+        let setoffs: Vec<SimpleSetoff<u64, i64>> =
+            [(1, 2, 10, 5), (2, 3, 5, 5), (3, 1, 5, 5), (2, 4, 3, 0)]
+                .into_iter()
+                .map(|(debtor, creditor, amount, set_off)| {
+                    SimpleSetoff::new(None, debtor, creditor, amount, set_off, amount - set_off)
+                })
+                .collect();
+        let plan = settlement_plan(&setoffs);
+        assert_eq!(settled(&plan), BTreeMap::from([(1, -5), (2, 2), (4, 3)]));
+        assert_eq!(plan.iter().map(|p| p.amount).sum::<i64>(), 5);
+    }
+}
@@ -0,0 +1,72 @@
+use core::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// The statistics of a clearing run.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunReport<Amount> {
+    /// The Net Internal Debt, i.e. the sum of the positive net positions of the firms
+    pub nid: Amount,
+    pub total_debt: Amount,
+    pub total_remainder: Amount,
+    pub total_cleared: Amount,
+    /// The part of `total_cleared` that was netted by the bilateral pre-pass
+    pub bilateral: Amount,
+    /// The part of `total_cleared` that was cleared by the min-cost flow (or along short cycles)
+    pub multilateral: Amount,
+    /// The amount that could be cleared in addition if the indivisible obligations could be set
+    /// off partially
+    pub lost: Amount,
+    /// `false` if the min-cost flow algo was cancelled (see `Clearing::optimal`)
+    pub optimal: bool,
+    pub firms: usize,
+    pub obligations: usize,
+    pub ineligible: usize,
+    pub indivisible: usize,
+    /// The number of iterations of the min-cost flow algorithm summed over all its runs (e.g. one
+    /// per component, and one per component and branch with indivisible obligations), see
+    /// `MinCostFlow::iterations`
+    pub solver_iterations: usize,
+    pub timings: Timings,
+}
+
+/// The wall-clock time of the phases of a clearing run, as measured by the clock set with
+/// `DefaultMtcs::with_clock` (zero without a clock).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timings {
+    /// Validating the input and computing the net positions
+    pub preparation: Duration,
+    /// Running the min-cost flow algorithm (including the bilateral pre-pass and the search over
+    /// the indivisible obligations)
+    pub clearing: Duration,
+    /// Assigning the cleared amounts to the obligations
+    pub allocation: Duration,
+}
+
+// Measures the time between laps with a clock (if any)
+pub(crate) struct Stopwatch {
+    clock: Option<fn() -> Duration>,
+    last: Duration,
+}
+
+impl Stopwatch {
+    pub(crate) fn start(clock: Option<fn() -> Duration>) -> Self {
+        Self {
+            clock,
+            last: clock.map(|now| now()).unwrap_or_default(),
+        }
+    }
+
+    // The time since the start or the previous lap
+    pub(crate) fn lap(&mut self) -> Duration {
+        match self.clock {
+            Some(now) => {
+                let time = now();
+                let lap = time.saturating_sub(self.last);
+                self.last = time;
+                lap
+            }
+            None => Duration::ZERO,
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use alloc::string::String;
+
 use serde::{Deserialize, Serialize};
 
 /// A set-off notice consisting of an obligation augmented with a set-off amount.
@@ -19,6 +21,27 @@ pub trait SetOff {
     fn amount(&self) -> Self::Amount;
     fn set_off(&self) -> Self::Amount;
     fn remainder(&self) -> Self::Amount;
+    /// The priority-of-claims of the underlying obligation (not recorded by default).
+    fn priority(&self) -> Option<Self::Amount> {
+        None
+    }
+    fn with_priority(self, _priority: Option<Self::Amount>) -> Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+    /// The reason why the underlying obligation wasn't eligible for set-off (if any, not recorded
+    /// by default).
+    fn reason(&self) -> Option<&str> {
+        None
+    }
+    fn with_reason(self, _reason: Option<String>) -> Self
+    where
+        Self: Sized,
+    {
+        self
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -30,6 +53,8 @@ pub struct SimpleSetoff<AccountId, Amount> {
     pub amount: Amount,
     pub set_off: Amount,
     pub remainder: Amount,
+    pub priority: Option<Amount>,
+    pub reason: Option<String>,
 }
 
 impl<AccountId, Amount> SetOff for SimpleSetoff<AccountId, Amount>
@@ -54,6 +79,8 @@ where
             amount,
             set_off,
             remainder,
+            priority: None,
+            reason: None,
         }
     }
 
@@ -80,4 +107,22 @@ where
     fn remainder(&self) -> Self::Amount {
         self.remainder
     }
+
+    fn priority(&self) -> Option<Self::Amount> {
+        self.priority
+    }
+
+    fn with_priority(mut self, priority: Option<Self::Amount>) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    fn with_reason(mut self, reason: Option<String>) -> Self {
+        self.reason = reason;
+        self
+    }
 }
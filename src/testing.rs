@@ -0,0 +1,20 @@
+//! Helpers shared by the unit tests of this crate.
+
+/// A xorshift generator of pseudo-random numbers, so that the tests can run over many generated
+/// inputs that are the same on every run.
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    /// Creates a generator from a non-zero `seed`.
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// The next pseudo-random number in `0..n`.
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % n
+    }
+}
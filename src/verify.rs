@@ -0,0 +1,310 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use num_traits::{CheckedAdd, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::id::Id;
+use crate::int::Int;
+use crate::obligation::Obligation;
+use crate::setoff::SetOff;
+
+/// An inconsistency between the submitted obligations and the set-off notices, as found by
+/// `verify`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Violation<AccountId, Amount> {
+    /// A submitted obligation without a set-off notice
+    MissingSetOff {
+        id: Option<usize>,
+        debtor: AccountId,
+        creditor: AccountId,
+        amount: Amount,
+    },
+    /// A set-off notice without a submitted obligation (with the same id, debtor, creditor and
+    /// amount)
+    UnknownSetOff {
+        id: Option<usize>,
+        debtor: AccountId,
+        creditor: AccountId,
+        amount: Amount,
+    },
+    /// A set-off notice whose set-off and remainder don't add up to its amount (or overflow)
+    InconsistentAmounts {
+        id: Option<usize>,
+        debtor: AccountId,
+        creditor: AccountId,
+        amount: Amount,
+        set_off: Amount,
+        remainder: Amount,
+    },
+    /// A set-off notice that sets off a negative amount or more than its amount
+    SetOffOutOfRange {
+        id: Option<usize>,
+        debtor: AccountId,
+        creditor: AccountId,
+        amount: Amount,
+        set_off: Amount,
+    },
+    /// A firm whose debts are set off by a different amount than its claims
+    UnbalancedFirm {
+        firm: AccountId,
+        as_debtor: Amount,
+        as_creditor: Amount,
+    },
+}
+
+/// The outcome of `verify`, i.e. the totals of the set-off notices and the violations found.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report<AccountId, Amount> {
+    pub total_debt: Amount,
+    pub total_set_off: Amount,
+    pub total_remainder: Amount,
+    pub violations: Vec<Violation<AccountId, Amount>>,
+}
+
+impl<AccountId, Amount> Report<AccountId, Amount> {
+    /// Checks if no violations were found.
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Audits set-off notices against the obligations they were computed from, independently of how
+/// they were computed.
+///
+/// Every obligation must have exactly one set-off notice with the same id, debtor, creditor and
+/// amount (in any order), every notice must set off between zero and its amount with the rest
+/// remaining, and every firm must have as much of its debts set off as of its claims. Notices that
+/// include cash payments (see `DefaultMtcs::run_with_liquidity`) don't balance on their own.
+///
+/// Returns `Error::AmountOverflow` if the total debt, set-off or remainder (in all or of a firm)
+/// overflows.
+pub fn verify<O, SO>(
+    obligations: &[O],
+    setoffs: &[SO],
+) -> Result<Report<O::AccountId, O::Amount>, Error>
+where
+    O: Obligation,
+    O::AccountId: Id,
+    O::Amount: Int + CheckedAdd,
+    SO: SetOff<AccountId = O::AccountId, Amount = O::Amount>,
+{
+    let mut violations = vec![];
+
+    // match the notices one-to-one with the obligations
+    let mut unmatched = obligations
+        .iter()
+        .fold(BTreeMap::<_, usize>::new(), |mut acc, o| {
+            *acc.entry((o.id(), o.debtor(), o.creditor(), o.amount()))
+                .or_default() += 1;
+            acc
+        });
+    for so in setoffs {
+        match unmatched.get_mut(&(so.id(), so.debtor(), so.creditor(), so.amount())) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => violations.push(Violation::UnknownSetOff {
+                id: so.id(),
+                debtor: so.debtor().clone(),
+                creditor: so.creditor().clone(),
+                amount: so.amount(),
+            }),
+        }
+    }
+    for o in obligations {
+        match unmatched.get_mut(&(o.id(), o.debtor(), o.creditor(), o.amount())) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                violations.push(Violation::MissingSetOff {
+                    id: o.id(),
+                    debtor: o.debtor().clone(),
+                    creditor: o.creditor().clone(),
+                    amount: o.amount(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    // every notice on its own
+    for so in setoffs {
+        if so.set_off().checked_add(&so.remainder()) != Some(so.amount()) {
+            violations.push(Violation::InconsistentAmounts {
+                id: so.id(),
+                debtor: so.debtor().clone(),
+                creditor: so.creditor().clone(),
+                amount: so.amount(),
+                set_off: so.set_off(),
+                remainder: so.remainder(),
+            });
+        }
+        if so.set_off() < O::Amount::zero() || so.set_off() > so.amount() {
+            violations.push(Violation::SetOffOutOfRange {
+                id: so.id(),
+                debtor: so.debtor().clone(),
+                creditor: so.creditor().clone(),
+                amount: so.amount(),
+                set_off: so.set_off(),
+            });
+        }
+    }
+
+    // the set-off of every firm as a debtor and as a creditor
+    let mut set_offs = BTreeMap::<_, (O::Amount, O::Amount)>::new();
+    for so in setoffs {
+        let as_debtor = &mut set_offs.entry(so.debtor()).or_default().0;
+        *as_debtor = checked_add(*as_debtor, so.set_off())?;
+        let as_creditor = &mut set_offs.entry(so.creditor()).or_default().1;
+        *as_creditor = checked_add(*as_creditor, so.set_off())?;
+    }
+    for (firm, (as_debtor, as_creditor)) in set_offs {
+        if as_debtor != as_creditor {
+            violations.push(Violation::UnbalancedFirm {
+                firm: firm.clone(),
+                as_debtor,
+                as_creditor,
+            });
+        }
+    }
+
+    Ok(Report {
+        total_debt: total(obligations.iter().map(|o| o.amount()))?,
+        total_set_off: total(setoffs.iter().map(|so| so.set_off()))?,
+        total_remainder: total(setoffs.iter().map(|so| so.remainder()))?,
+        violations,
+    })
+}
+
+// Add `b` to `a`, unless the sum overflows
+fn checked_add<Amount: CheckedAdd>(a: Amount, b: Amount) -> Result<Amount, Error> {
+    a.checked_add(&b).ok_or(Error::AmountOverflow)
+}
+
+// Sum up the `amounts`, unless the total overflows
+fn total<Amount: Int + CheckedAdd>(
+    mut amounts: impl Iterator<Item = Amount>,
+) -> Result<Amount, Error> {
+    amounts.try_fold(Amount::zero(), checked_add)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obligation::SimpleObligation;
+    use crate::setoff::SimpleSetoff;
+
+    // A cycle of three firms that is set off by 5
+    fn obligations() -> Vec<SimpleObligation<u64, i64>> {
+        [(1, 2, 10), (2, 3, 5), (3, 1, 7)]
+            .into_iter()
+            .enumerate()
+            .map(|(id, (debtor, creditor, amount))| {
+                SimpleObligation::new(Some(id), debtor, creditor, amount).unwrap()
+            })
+            .collect()
+    }
+
+    fn setoffs(set_offs: &[(i64, i64)]) -> Vec<SimpleSetoff<u64, i64>> {
+        obligations()
+            .iter()
+            .zip(set_offs)
+            .map(|(o, (set_off, remainder))| {
+                SimpleSetoff::new(
+                    o.id(),
+                    *o.debtor(),
+                    *o.creditor(),
+                    o.amount(),
+                    *set_off,
+                    *remainder,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid_setoffs_have_no_violations() {
+        let report = verify(&obligations(), &setoffs(&[(5, 5), (5, 0), (5, 2)])).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(
+            (
+                report.total_debt,
+                report.total_set_off,
+                report.total_remainder
+            ),
+            (22, 15, 7)
+        );
+    }
+
+    #[test]
+    fn violations_are_reported() {
+        let on = obligations();
+
+        // the last obligation has no set-off, and a set-off was made up instead
+        let mut made_up = setoffs(&[(5, 5), (5, 0), (0, 7)]);
+        made_up[2].amount = 8;
+        made_up[2].remainder = 8;
+        assert_eq!(
+            verify(&on, &made_up).unwrap().violations,
+            [
+                Violation::UnknownSetOff {
+                    id: Some(2),
+                    debtor: 3,
+                    creditor: 1,
+                    amount: 8,
+                },
+                Violation::MissingSetOff {
+                    id: Some(2),
+                    debtor: 3,
+                    creditor: 1,
+                    amount: 7,
+                },
+                Violation::UnbalancedFirm {
+                    firm: 1,
+                    as_debtor: 5,
+                    as_creditor: 0,
+                },
+                Violation::UnbalancedFirm {
+                    firm: 3,
+                    as_debtor: 0,
+                    as_creditor: 5,
+                },
+            ]
+        );
+
+        // the set-off of the middle obligation exceeds its amount, and its remainder overflows
+        let violations = verify(&on, &setoffs(&[(6, 4), (6, i64::MAX - 5), (6, 1)]))
+            .unwrap()
+            .violations;
+        assert_eq!(
+            violations,
+            [
+                Violation::InconsistentAmounts {
+                    id: Some(1),
+                    debtor: 2,
+                    creditor: 3,
+                    amount: 5,
+                    set_off: 6,
+                    remainder: i64::MAX - 5,
+                },
+                Violation::SetOffOutOfRange {
+                    id: Some(1),
+                    debtor: 2,
+                    creditor: 3,
+                    amount: 5,
+                    set_off: 6,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn overflowing_totals_are_an_error() {
+        let on = obligations();
+        let overflowing = setoffs(&[(5, 5), (5, i64::MAX), (5, 2)]);
+        assert!(matches!(
+            verify(&on, &overflowing),
+            Err(Error::AmountOverflow)
+        ));
+    }
+}